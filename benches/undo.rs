@@ -0,0 +1,168 @@
+// Compares the old undo model (a full copy of the canvas before every edit, at most 50 levels) with the
+// cell-delta history, on a large canvas with small edits (rectangles on an opaque layer, pencil strokes on
+// a transparent one).
+//
+//     cargo bench --bench undo
+
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::Duration;
+use std::time::Instant;
+
+use appcui::prelude::*;
+
+use ascii_painter::history;
+use ascii_painter::history::History;
+use ascii_painter::layer::Cells;
+use ascii_painter::layer::Layer;
+
+const SIZE: u32 = 500;
+const EDITS: usize = 200;
+const OLD_LEVELS: usize = 50;
+
+struct Measurement {
+    edit: Duration,
+    undo: Duration,
+    memory: usize,
+}
+
+const RECTANGLE: Character = Character {
+    code: '#',
+    foreground: Color::Yellow,
+    background: Color::Blue,
+    flags: CharFlags::None,
+};
+
+// a 10x4 rectangle at a different place for every edit
+fn rectangle(step: usize) -> Rect {
+    let x = (step * 37 % (SIZE as usize - 10)) as i32;
+    let y = (step * 11 % (SIZE as usize - 4)) as i32;
+    Rect::with_size(x, y, 10, 4)
+}
+
+// a diagonal pencil stroke of 20 cells at a different place for every edit (returns the changed area)
+fn stroke(cells: &mut Cells, step: usize) -> Rect {
+    let x = (step * 37 % (SIZE as usize - 20)) as i32;
+    let y = (step * 11 % (SIZE as usize - 20)) as i32;
+    for i in 0..20 {
+        cells.write_char(
+            x + i,
+            y + i,
+            Character::new('*', Color::White, Color::Transparent, CharFlags::None),
+        );
+    }
+    Rect::with_size(x, y, 20, 20)
+}
+
+// the copy that `save_state` used to make before every edit
+fn copy_surface(surface: &Surface) -> Surface {
+    let size = surface.size();
+    let mut copy = Surface::new(size.width, size.height);
+    for y in 0..size.height as i32 {
+        for x in 0..size.width as i32 {
+            if let Some(ch) = surface.char(x, y) {
+                copy.write_char(x, y, *ch);
+            }
+        }
+    }
+    copy
+}
+
+fn snapshots() -> Measurement {
+    let mut surface = Surface::new(SIZE, SIZE);
+    let mut undo_stack: Vec<Surface> = Vec::new();
+    let start = Instant::now();
+    for step in 0..EDITS {
+        undo_stack.push(copy_surface(&surface));
+        if undo_stack.len() > OLD_LEVELS {
+            undo_stack.remove(0);
+        }
+        surface.fill_rect(rectangle(step), RECTANGLE);
+    }
+    let edit = start.elapsed() / EDITS as u32;
+    let memory = undo_stack.len() * (SIZE * SIZE) as usize * size_of::<Character>();
+    let levels = undo_stack.len() as u32;
+    let start = Instant::now();
+    while let Some(previous) = undo_stack.pop() {
+        surface = previous;
+    }
+    black_box(&surface);
+    Measurement {
+        edit,
+        undo: start.elapsed() / levels,
+        memory,
+    }
+}
+
+// `known_area`: the edited rectangle is given to the history (every tool reports it); otherwise the whole
+// layer is searched for changes (clear)
+fn deltas(known_area: bool) -> Measurement {
+    let mut layers = vec![Layer::with_surface("Background", Surface::new(SIZE, SIZE))];
+    let mut history = History::new(&layers, 0, history::DEFAULT_BUDGET);
+    let start = Instant::now();
+    for step in 0..EDITS {
+        let area = rectangle(step);
+        layers[0]
+            .cells
+            .draw(area, |surface| surface.fill_rect(area, RECTANGLE));
+        history.record_cells("Rectangle", &layers, 0, known_area.then_some(area));
+    }
+    let edit = start.elapsed() / EDITS as u32;
+    let memory = history.memory();
+    let mut levels = 0;
+    let start = Instant::now();
+    while history.undo(&mut layers).is_some() {
+        levels += 1;
+    }
+    black_box(&layers);
+    Measurement {
+        edit,
+        undo: start.elapsed() / levels,
+        memory,
+    }
+}
+
+// pencil strokes on a transparent layer (a layer above the background)
+fn strokes() -> Measurement {
+    let mut layers = vec![Layer::new("Layer", SIZE, SIZE)];
+    let mut history = History::new(&layers, 0, history::DEFAULT_BUDGET);
+    let start = Instant::now();
+    for step in 0..EDITS {
+        let area = stroke(&mut layers[0].cells, step);
+        history.record_cells("Pencil", &layers, 0, Some(area));
+    }
+    let edit = start.elapsed() / EDITS as u32;
+    let memory = history.memory();
+    let mut levels = 0;
+    let start = Instant::now();
+    while history.undo(&mut layers).is_some() {
+        levels += 1;
+    }
+    black_box(&layers);
+    Measurement {
+        edit,
+        undo: start.elapsed() / levels,
+        memory,
+    }
+}
+
+fn main() {
+    println!(
+        "{} edits (10x4 rectangles / 20 cell strokes) on a {}x{} canvas",
+        EDITS, SIZE, SIZE
+    );
+    for (name, result) in [
+        ("snapshots", snapshots()),
+        ("deltas (known area)", deltas(true)),
+        ("deltas (full search)", deltas(false)),
+        ("pencil (transparent)", strokes()),
+    ] {
+        println!(
+            "{:>22}: {:>9.4} ms per edit, {:>9.4} ms per undo, {:>8} KB kept",
+            name,
+            result.edit.as_secs_f64() * 1000.0,
+            result.undo.as_secs_f64() * 1000.0,
+            result.memory / 1024
+        );
+    }
+}
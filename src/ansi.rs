@@ -0,0 +1,604 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use appcui::prelude::*;
+
+use crate::colors;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum AnsiColors {
+    Colors16,
+    Colors256,
+    TrueColor,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct Attributes {
+    fore: Color,
+    back: Color,
+    flags: CharFlags,
+}
+
+impl Attributes {
+    const DEFAULT: Attributes = Attributes {
+        fore: Color::Transparent,
+        back: Color::Transparent,
+        flags: CharFlags::None,
+    };
+}
+
+// SGR parameters that select a color. The 16 named colors always use the basic codes (so that they follow the
+// terminal palette), RGB colors are reduced to the requested color depth.
+fn color_params(params: &mut Vec<String>, color: Color, background: bool, mode: AnsiColors) {
+    if let Some(idx) = colors::index(color) {
+        let ansi = colors::to_ansi_index(idx);
+        let base = match (background, ansi >= 8) {
+            (false, false) => 30,
+            (false, true) => 90 - 8,
+            (true, false) => 40,
+            (true, true) => 100 - 8,
+        };
+        params.push((base + ansi as u32).to_string());
+        return;
+    }
+    let Some(rgb) = colors::rgb(color) else {
+        params.push(if background { "49" } else { "39" }.to_string());
+        return;
+    };
+    let prefix = if background { 48 } else { 38 };
+    match mode {
+        AnsiColors::Colors16 => color_params(params, colors::nearest(rgb), background, mode),
+        AnsiColors::Colors256 => params.push(format!("{};5;{}", prefix, colors::to_xterm256(rgb))),
+        AnsiColors::TrueColor => params.push(format!("{};2;{};{};{}", prefix, rgb.0, rgb.1, rgb.2)),
+    }
+}
+
+// SGR sequence that changes the attributes from `from` to `to` (only the parts that differ are emitted)
+fn transition(output: &mut String, from: Attributes, to: Attributes, mode: AnsiColors) {
+    if from == to {
+        return;
+    }
+    let mut params = Vec::new();
+    let mut from = from;
+    // flags can only be turned off one by one with codes that are not widely supported, so reset instead
+    let removed = [CharFlags::Bold, CharFlags::Italic, CharFlags::Underline]
+        .iter()
+        .any(|f| from.flags.contains(*f) && !to.flags.contains(*f));
+    if removed {
+        params.push("0".to_string());
+        from = Attributes::DEFAULT;
+    }
+    for (flag, code) in [
+        (CharFlags::Bold, "1"),
+        (CharFlags::Italic, "3"),
+        (CharFlags::Underline, "4"),
+    ] {
+        if to.flags.contains(flag) && !from.flags.contains(flag) {
+            params.push(code.to_string());
+        }
+    }
+    if from.fore != to.fore {
+        color_params(&mut params, to.fore, false, mode);
+    }
+    if from.back != to.back {
+        color_params(&mut params, to.back, true, mode);
+    }
+    if !params.is_empty() {
+        let _ = write!(output, "\x1b[{}m", params.join(";"));
+    }
+}
+
+pub(crate) fn export(surface: &Surface, mode: AnsiColors) -> String {
+    let size = surface.size();
+    let mut output = String::with_capacity((size.width * size.height * 2) as usize);
+    for y in 0..size.height as i32 {
+        let mut current = Attributes::DEFAULT;
+        for x in 0..size.width as i32 {
+            let Some(ch) = surface.char(x, y) else {
+                continue;
+            };
+            let attr = Attributes {
+                fore: ch.foreground,
+                back: ch.background,
+                flags: ch.flags,
+            };
+            transition(&mut output, current, attr, mode);
+            current = attr;
+            output.push(if ch.code == 0 as char { ' ' } else { ch.code });
+        }
+        // reset at the end of every line so that the background does not bleed into the next one
+        if current != Attributes::DEFAULT {
+            output.push_str("\x1b[0m");
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// characters for the CP437 bytes 0x80..=0xFF
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+// glyphs that CP437 shows for the control bytes 0x00..=0x1F
+const CP437_LOW: &str = " ☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+
+const ESC: char = '\x1b';
+const SUB: char = '\x1a';
+
+// guards against sequences such as ESC[9999B creating huge surfaces
+const MAX_WIDTH: usize = 1000;
+const MAX_HEIGHT: usize = 10000;
+
+pub(crate) struct AnsiImport {
+    pub surface: Surface,
+    // unsupported escape sequences that were skipped (and how many times they were found)
+    pub skipped: Vec<(String, usize)>,
+}
+
+struct Sauce {
+    width: Option<usize>,
+    data_len: usize,
+}
+
+// SAUCE is a 128 byte record (optionally preceded by a comment block) appended to many ANSI art files
+fn parse_sauce(data: &[u8]) -> Sauce {
+    let len = data.len();
+    if len < 128 || &data[len - 128..len - 121] != b"SAUCE00" {
+        return Sauce {
+            width: None,
+            data_len: len,
+        };
+    }
+    let record = &data[len - 128..];
+    let data_type = record[94];
+    let width = u16::from_le_bytes([record[96], record[97]]) as usize;
+    let comments = record[104] as usize;
+    let mut data_len = len - 128;
+    let comments_len = 5 + comments * 64;
+    if comments > 0
+        && data_len >= comments_len
+        && &data[data_len - comments_len..data_len - comments_len + 5] == b"COMNT"
+    {
+        data_len -= comments_len;
+    }
+    Sauce {
+        // data type 1 = character based files (ASCII, ANSI, ...), TInfo1 holds the width
+        width: if data_type == 1 && width > 0 {
+            Some(width.min(MAX_WIDTH))
+        } else {
+            None
+        },
+        data_len,
+    }
+}
+
+// UTF-8 input is used as is, anything else is considered a legacy CP437 file
+fn decode(data: &[u8]) -> (Vec<char>, bool) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        return (text.chars().collect(), false);
+    }
+    let high: Vec<char> = CP437_HIGH.chars().collect();
+    let low: Vec<char> = CP437_LOW.chars().collect();
+    let chars = data
+        .iter()
+        .map(|b| match *b {
+            0x1b | 0x1a | b'\r' | b'\n' | b'\t' => *b as char,
+            0x00..=0x1f => low[*b as usize],
+            0x7f => '⌂',
+            0x80..=0xff => high[(*b - 0x80) as usize],
+            _ => *b as char,
+        })
+        .collect();
+    (chars, true)
+}
+
+struct Parser {
+    rows: Vec<Vec<Character>>,
+    x: usize,
+    y: usize,
+    saved: (usize, usize),
+    fore: Color,
+    back: Color,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    blink: bool,
+    reverse: bool,
+    // legacy (CP437) files use bold for bright foregrounds and blink for bright backgrounds (iCE colors)
+    legacy: bool,
+    wrap: Option<usize>,
+    skipped: BTreeMap<String, usize>,
+}
+
+impl Parser {
+    const BLANK: Character = Character {
+        code: ' ',
+        foreground: Color::Silver,
+        background: Color::Black,
+        flags: CharFlags::None,
+    };
+
+    fn new(legacy: bool, wrap: Option<usize>) -> Self {
+        Self {
+            rows: Vec::new(),
+            x: 0,
+            y: 0,
+            saved: (0, 0),
+            fore: Color::Silver,
+            back: Color::Black,
+            bold: false,
+            italic: false,
+            underline: false,
+            blink: false,
+            reverse: false,
+            legacy,
+            wrap,
+            skipped: BTreeMap::new(),
+        }
+    }
+
+    fn reset_attributes(&mut self) {
+        self.fore = Color::Silver;
+        self.back = Color::Black;
+        self.bold = false;
+        self.italic = false;
+        self.underline = false;
+        self.blink = false;
+        self.reverse = false;
+    }
+
+    fn brighten(color: Color) -> Color {
+        match colors::index(color) {
+            Some(i) if i < 8 => colors::named(i + 8),
+            _ => color,
+        }
+    }
+
+    fn current(&self, code: char) -> Character {
+        let mut fore = self.fore;
+        let mut back = self.back;
+        let mut flags = CharFlags::None;
+        if self.italic {
+            flags |= CharFlags::Italic;
+        }
+        if self.underline {
+            flags |= CharFlags::Underline;
+        }
+        if self.bold {
+            if self.legacy {
+                fore = Self::brighten(fore);
+            } else {
+                flags |= CharFlags::Bold;
+            }
+        }
+        if self.blink && self.legacy {
+            back = Self::brighten(back);
+        }
+        if self.reverse {
+            std::mem::swap(&mut fore, &mut back);
+        }
+        Character::new(code, fore, back, flags)
+    }
+
+    fn put(&mut self, code: char) {
+        if self.wrap.is_some_and(|w| self.x >= w) {
+            self.x = 0;
+            self.y += 1;
+        }
+        if self.x >= MAX_WIDTH || self.y >= MAX_HEIGHT {
+            return;
+        }
+        let ch = self.current(code);
+        if self.rows.len() <= self.y {
+            self.rows.resize(self.y + 1, Vec::new());
+        }
+        let row = &mut self.rows[self.y];
+        if row.len() <= self.x {
+            row.resize(self.x + 1, Self::BLANK);
+        }
+        row[self.x] = ch;
+        self.x += 1;
+    }
+
+    fn skip(&mut self, sequence: String) {
+        *self.skipped.entry(sequence).or_insert(0) += 1;
+    }
+
+    fn sgr(&mut self, params: &[usize]) {
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => self.reset_attributes(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                5 => self.blink = true,
+                7 => self.reverse = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                25 => self.blink = false,
+                27 => self.reverse = false,
+                n @ 30..=37 => self.fore = colors::named(colors::from_ansi_index((n - 30) as u8)),
+                39 => self.fore = Color::Silver,
+                n @ 40..=47 => self.back = colors::named(colors::from_ansi_index((n - 40) as u8)),
+                49 => self.back = Color::Black,
+                n @ 90..=97 => {
+                    self.fore = colors::named(colors::from_ansi_index((n - 90 + 8) as u8))
+                }
+                n @ 100..=107 => {
+                    self.back = colors::named(colors::from_ansi_index((n - 100 + 8) as u8))
+                }
+                n @ (38 | 48) => {
+                    let color = match params.get(i + 1) {
+                        Some(5) if i + 2 < params.len() => {
+                            let c = colors::from_xterm256(params[i + 2].min(255) as u8);
+                            i += 2;
+                            Some(c)
+                        }
+                        Some(2) if i + 4 < params.len() => {
+                            let c = Color::RGB(
+                                params[i + 2].min(255) as u8,
+                                params[i + 3].min(255) as u8,
+                                params[i + 4].min(255) as u8,
+                            );
+                            i += 4;
+                            Some(c)
+                        }
+                        _ => None,
+                    };
+                    match color {
+                        Some(c) if n == 38 => self.fore = c,
+                        Some(c) => self.back = c,
+                        None => {
+                            self.skip(format!("ESC[{}m", n));
+                            return;
+                        }
+                    }
+                }
+                n => self.skip(format!("ESC[{}m", n)),
+            }
+            i += 1;
+        }
+    }
+
+    fn csi(&mut self, raw: &str, final_byte: char) {
+        // private sequences (ESC[?25h, ...) only change terminal modes
+        if raw.starts_with(['<', '=', '>', '?']) {
+            self.skip(format!("ESC[{}{}", raw, final_byte));
+            return;
+        }
+        let params: Vec<usize> = raw
+            .split(';')
+            .map(|p| p.parse::<usize>().unwrap_or(0))
+            .collect();
+        let n = params.first().copied().unwrap_or(0).max(1);
+        match final_byte {
+            'm' => self.sgr(&params),
+            'A' => self.y = self.y.saturating_sub(n),
+            'B' => self.y = self.y.saturating_add(n).min(MAX_HEIGHT),
+            'C' => self.x = self.x.saturating_add(n).min(MAX_WIDTH),
+            'D' => self.x = self.x.saturating_sub(n),
+            'E' => {
+                self.y = self.y.saturating_add(n).min(MAX_HEIGHT);
+                self.x = 0;
+            }
+            'F' => {
+                self.y = self.y.saturating_sub(n);
+                self.x = 0;
+            }
+            'G' => self.x = (n - 1).min(MAX_WIDTH),
+            'H' | 'f' => {
+                self.y = (n - 1).min(MAX_HEIGHT);
+                self.x = (params.get(1).copied().unwrap_or(0).max(1) - 1).min(MAX_WIDTH);
+            }
+            's' => self.saved = (self.x, self.y),
+            'u' => (self.x, self.y) = self.saved,
+            'J' if params[0] == 2 => {
+                self.rows.clear();
+                self.x = 0;
+                self.y = 0;
+            }
+            'K' => {
+                let x = self.x;
+                if let Some(row) = self.rows.get_mut(self.y) {
+                    row.truncate(x);
+                }
+            }
+            _ => self.skip(format!("ESC[{}{}", raw, final_byte)),
+        }
+    }
+
+    fn run(&mut self, chars: &[char]) {
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            match c {
+                SUB => break,
+                '\r' => self.x = 0,
+                '\n' => {
+                    self.x = 0;
+                    self.y = (self.y + 1).min(MAX_HEIGHT);
+                }
+                '\t' => self.x = (self.x / 8 + 1) * 8,
+                ESC => {
+                    if chars.get(i) != Some(&'[') {
+                        let next = chars.get(i).copied().unwrap_or(' ');
+                        self.skip(format!("ESC{}", next));
+                        i += 1;
+                        continue;
+                    }
+                    i += 1;
+                    let start = i;
+                    if chars.get(i).is_some_and(|c| matches!(c, '<'..='?')) {
+                        i += 1;
+                    }
+                    while i < chars.len() && matches!(chars[i], '0'..='9' | ';' | ' '..='/') {
+                        i += 1;
+                    }
+                    let raw: String = chars[start..i].iter().collect();
+                    if let Some(final_byte) = chars.get(i).copied() {
+                        i += 1;
+                        self.csi(&raw, final_byte);
+                    }
+                }
+                c if (c as u32) < 0x20 => {}
+                c => self.put(c),
+            }
+        }
+    }
+
+    fn into_surface(mut self) -> AnsiImport {
+        while self.rows.last().is_some_and(|r| r.is_empty()) {
+            self.rows.pop();
+        }
+        let width = self
+            .wrap
+            .unwrap_or(0)
+            .max(self.rows.iter().map(|r| r.len()).max().unwrap_or(0))
+            .max(1);
+        let height = self.rows.len().max(1);
+        let mut surface = Surface::new(width as u32, height as u32);
+        surface.clear(Self::BLANK);
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, ch) in row.iter().enumerate() {
+                surface.write_char(x as i32, y as i32, *ch);
+            }
+        }
+        AnsiImport {
+            surface,
+            skipped: self.skipped.into_iter().collect(),
+        }
+    }
+}
+
+pub(crate) fn import(data: &[u8]) -> AnsiImport {
+    let sauce = parse_sauce(data);
+    let (chars, legacy) = decode(&data[..sauce.data_len]);
+    // legacy art is drawn for 80 column terminals and relies on the line wrapping there
+    let wrap = sauce.width.or(if legacy { Some(80) } else { None });
+    let mut parser = Parser::new(legacy, wrap);
+    parser.run(&chars);
+    parser.into_surface()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the codes of a row of the imported surface
+    fn row(import: &AnsiImport, y: i32) -> String {
+        (0..import.surface.size().width as i32)
+            .map(|x| import.surface.char(x, y).unwrap().code)
+            .collect()
+    }
+
+    fn sauce(width: u16) -> Vec<u8> {
+        let mut record = vec![0; 128];
+        record[..7].copy_from_slice(b"SAUCE00");
+        record[94] = 1;
+        record[96..98].copy_from_slice(&width.to_le_bytes());
+        record
+    }
+
+    #[test]
+    fn sgr_state_is_kept_until_changed() {
+        let import = import(b"\x1b[1;31mA\x1b[44mB\x1b[0mC\x1b[7;32mD");
+        let cell = |x| *import.surface.char(x, 0).unwrap();
+        assert_eq!(
+            cell(0),
+            Character::new('A', Color::DarkRed, Color::Black, CharFlags::Bold)
+        );
+        assert_eq!(
+            cell(1),
+            Character::new('B', Color::DarkRed, Color::DarkBlue, CharFlags::Bold)
+        );
+        assert_eq!(
+            cell(2),
+            Character::new('C', Color::Silver, Color::Black, CharFlags::None)
+        );
+        // reverse swaps the colors
+        assert_eq!(
+            cell(3),
+            Character::new('D', Color::Black, Color::DarkGreen, CharFlags::None)
+        );
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let import = import(b"AB\x1b[2DC\x1b[2;3HD\x1b[sE\x1b[H\x1b[uF\x1b[3A\x1b[2CG");
+        assert_eq!(import.surface.size(), Size::new(7, 2));
+        assert_eq!(row(&import, 0), "CB    G");
+        assert_eq!(row(&import, 1), "  DF   ");
+    }
+
+    #[test]
+    fn legacy_files_are_decoded_as_cp437() {
+        let import = import(b"\xdb\xb0\x01A\x80\x1b[1;31m\xdb");
+        assert_eq!(&row(&import, 0)[..], format!("█░☺AÇ█{}", " ".repeat(74)));
+        // bold makes the foreground bright instead of setting the flag
+        assert_eq!(
+            *import.surface.char(5, 0).unwrap(),
+            Character::new('█', Color::Red, Color::Black, CharFlags::None)
+        );
+    }
+
+    #[test]
+    fn sauce_sets_the_width() {
+        let mut data = b"ABCDEF\x1a".to_vec();
+        data.extend(sauce(3));
+        let import = import(&data);
+        assert_eq!(import.surface.size(), Size::new(3, 2));
+        assert_eq!(row(&import, 0), "ABC");
+        assert_eq!(row(&import, 1), "DEF");
+    }
+
+    #[test]
+    fn only_legacy_files_wrap_at_80_columns() {
+        let legacy = import(&[0xb0; 100]);
+        assert_eq!(legacy.surface.size(), Size::new(80, 2));
+        assert_eq!(
+            row(&legacy, 1),
+            format!("{}{}", "░".repeat(20), " ".repeat(60))
+        );
+        let utf8 = import(&[b'x'; 100]);
+        assert_eq!(utf8.surface.size(), Size::new(100, 1));
+    }
+
+    #[test]
+    fn skipped_sequences_are_reported() {
+        let import = import(b"\x1b[?25hA\x1b[5nB\x1b[5n\x1b(0\x1b[38;9mC");
+        assert_eq!(row(&import, 0), "AB0C");
+        assert_eq!(
+            import.skipped,
+            [
+                ("ESC(".to_string(), 1),
+                ("ESC[38m".to_string(), 1),
+                ("ESC[5n".to_string(), 2),
+                ("ESC[?25h".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_sequences_do_not_panic() {
+        for data in [
+            &b"\x1b"[..],
+            b"\x1b[",
+            b"A\x1b[1;3",
+            b"\x1b[38;5",
+            b"\x1b[38;2;1m",
+            b"\x1b[48;5m",
+            b"\x1b[;;;H",
+            b"\x1b[99999999999999999999999999B",
+            b"A\x1b[18446744073709551615C\n\x1b[18446744073709551615E",
+            b"\x1b[0D\x1b[0A\x1b[0GX",
+            b"\xff\x1b[",
+        ] {
+            let import = import(data);
+            let size = import.surface.size();
+            assert!(size.width >= 1 && size.height >= 1);
+        }
+    }
+}
@@ -0,0 +1,709 @@
+use crate::box_join::{self, D, DOWN, H, LEFT, RIGHT, UP};
+
+pub(crate) const WIDTH: usize = 8;
+pub(crate) const HEIGHT: usize = 13;
+
+// a glyph is stored as one byte per row (MSB = leftmost pixel)
+pub(crate) type Glyph = [u8; HEIGHT];
+
+// center of a cell (where box drawing lines meet)
+const CX: usize = 3;
+const CY: usize = 6;
+
+// glyphs 0x20..=0x7E of the X11 misc-fixed 8x13 font (public domain), one byte per row, MSB = leftmost pixel
+static ASCII: [[u8; HEIGHT]; 95] = [
+    // ' '
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '!'
+    [
+        0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00, 0x00,
+    ],
+    // '"'
+    [
+        0x00, 0x00, 0x24, 0x24, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '#'
+    [
+        0x00, 0x00, 0x00, 0x24, 0x24, 0x7E, 0x24, 0x7E, 0x24, 0x24, 0x00, 0x00, 0x00,
+    ],
+    // '$'
+    [
+        0x00, 0x00, 0x10, 0x3C, 0x50, 0x50, 0x38, 0x14, 0x14, 0x78, 0x10, 0x00, 0x00,
+    ],
+    // '%'
+    [
+        0x00, 0x00, 0x22, 0x52, 0x24, 0x08, 0x08, 0x10, 0x24, 0x2A, 0x44, 0x00, 0x00,
+    ],
+    // '&'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x48, 0x48, 0x30, 0x4A, 0x44, 0x3A, 0x00, 0x00,
+    ],
+    // "'"
+    [
+        0x00, 0x00, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '('
+    [
+        0x00, 0x00, 0x04, 0x08, 0x08, 0x10, 0x10, 0x10, 0x08, 0x08, 0x04, 0x00, 0x00,
+    ],
+    // ')'
+    [
+        0x00, 0x00, 0x20, 0x10, 0x10, 0x08, 0x08, 0x08, 0x10, 0x10, 0x20, 0x00, 0x00,
+    ],
+    // '*'
+    [
+        0x00, 0x00, 0x24, 0x18, 0x7E, 0x18, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '+'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x10, 0x10, 0x7C, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // ','
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x30, 0x40, 0x00,
+    ],
+    // '-'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '.'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00,
+    ],
+    // '/'
+    [
+        0x00, 0x00, 0x02, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x80, 0x00, 0x00,
+    ],
+    // '0'
+    [
+        0x00, 0x00, 0x18, 0x24, 0x42, 0x42, 0x42, 0x42, 0x42, 0x24, 0x18, 0x00, 0x00,
+    ],
+    // '1'
+    [
+        0x00, 0x00, 0x10, 0x30, 0x50, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00,
+    ],
+    // '2'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x02, 0x04, 0x18, 0x20, 0x40, 0x7E, 0x00, 0x00,
+    ],
+    // '3'
+    [
+        0x00, 0x00, 0x7E, 0x02, 0x04, 0x08, 0x1C, 0x02, 0x02, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // '4'
+    [
+        0x00, 0x00, 0x04, 0x0C, 0x14, 0x24, 0x44, 0x44, 0x7E, 0x04, 0x04, 0x00, 0x00,
+    ],
+    // '5'
+    [
+        0x00, 0x00, 0x7E, 0x40, 0x40, 0x5C, 0x62, 0x02, 0x02, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // '6'
+    [
+        0x00, 0x00, 0x1C, 0x20, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // '7'
+    [
+        0x00, 0x00, 0x7E, 0x02, 0x04, 0x08, 0x08, 0x10, 0x10, 0x20, 0x20, 0x00, 0x00,
+    ],
+    // '8'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x3C, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // '9'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x46, 0x3A, 0x02, 0x02, 0x04, 0x38, 0x00, 0x00,
+    ],
+    // ':'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00,
+    ],
+    // ';'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00, 0x00, 0x38, 0x30, 0x40, 0x00,
+    ],
+    // '<'
+    [
+        0x00, 0x00, 0x02, 0x04, 0x08, 0x10, 0x20, 0x10, 0x08, 0x04, 0x02, 0x00, 0x00,
+    ],
+    // '='
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '>'
+    [
+        0x00, 0x00, 0x40, 0x20, 0x10, 0x08, 0x04, 0x08, 0x10, 0x20, 0x40, 0x00, 0x00,
+    ],
+    // '?'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x02, 0x04, 0x08, 0x08, 0x00, 0x08, 0x00, 0x00,
+    ],
+    // '@'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x4E, 0x52, 0x56, 0x4A, 0x40, 0x3C, 0x00, 0x00,
+    ],
+    // 'A'
+    [
+        0x00, 0x00, 0x18, 0x24, 0x42, 0x42, 0x42, 0x7E, 0x42, 0x42, 0x42, 0x00, 0x00,
+    ],
+    // 'B'
+    [
+        0x00, 0x00, 0x78, 0x44, 0x42, 0x44, 0x78, 0x44, 0x42, 0x44, 0x78, 0x00, 0x00,
+    ],
+    // 'C'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x40, 0x40, 0x40, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'D'
+    [
+        0x00, 0x00, 0x78, 0x44, 0x42, 0x42, 0x42, 0x42, 0x42, 0x44, 0x78, 0x00, 0x00,
+    ],
+    // 'E'
+    [
+        0x00, 0x00, 0x7E, 0x40, 0x40, 0x40, 0x78, 0x40, 0x40, 0x40, 0x7E, 0x00, 0x00,
+    ],
+    // 'F'
+    [
+        0x00, 0x00, 0x7E, 0x40, 0x40, 0x40, 0x78, 0x40, 0x40, 0x40, 0x40, 0x00, 0x00,
+    ],
+    // 'G'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x40, 0x4E, 0x42, 0x46, 0x3A, 0x00, 0x00,
+    ],
+    // 'H'
+    [
+        0x00, 0x00, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x00, 0x00,
+    ],
+    // 'I'
+    [
+        0x00, 0x00, 0x7C, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00,
+    ],
+    // 'J'
+    [
+        0x00, 0x00, 0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x44, 0x38, 0x00, 0x00,
+    ],
+    // 'K'
+    [
+        0x00, 0x00, 0x42, 0x44, 0x48, 0x50, 0x60, 0x50, 0x48, 0x44, 0x42, 0x00, 0x00,
+    ],
+    // 'L'
+    [
+        0x00, 0x00, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x7E, 0x00, 0x00,
+    ],
+    // 'M'
+    [
+        0x00, 0x00, 0x82, 0x82, 0xC6, 0xAA, 0x92, 0x92, 0x82, 0x82, 0x82, 0x00, 0x00,
+    ],
+    // 'N'
+    [
+        0x00, 0x00, 0x42, 0x42, 0x62, 0x52, 0x4A, 0x46, 0x42, 0x42, 0x42, 0x00, 0x00,
+    ],
+    // 'O'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'P'
+    [
+        0x00, 0x00, 0x7C, 0x42, 0x42, 0x42, 0x7C, 0x40, 0x40, 0x40, 0x40, 0x00, 0x00,
+    ],
+    // 'Q'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x42, 0x42, 0x52, 0x4A, 0x3C, 0x02, 0x00,
+    ],
+    // 'R'
+    [
+        0x00, 0x00, 0x7C, 0x42, 0x42, 0x42, 0x7C, 0x50, 0x48, 0x44, 0x42, 0x00, 0x00,
+    ],
+    // 'S'
+    [
+        0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x3C, 0x02, 0x02, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'T'
+    [
+        0x00, 0x00, 0xFE, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00,
+    ],
+    // 'U'
+    [
+        0x00, 0x00, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'V'
+    [
+        0x00, 0x00, 0x82, 0x82, 0x44, 0x44, 0x44, 0x28, 0x28, 0x28, 0x10, 0x00, 0x00,
+    ],
+    // 'W'
+    [
+        0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x92, 0x92, 0x92, 0xAA, 0x44, 0x00, 0x00,
+    ],
+    // 'X'
+    [
+        0x00, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x28, 0x44, 0x82, 0x82, 0x00, 0x00,
+    ],
+    // 'Y'
+    [
+        0x00, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00,
+    ],
+    // 'Z'
+    [
+        0x00, 0x00, 0x7E, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x40, 0x7E, 0x00, 0x00,
+    ],
+    // '['
+    [
+        0x00, 0x00, 0x3C, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x3C, 0x00, 0x00,
+    ],
+    // '\\'
+    [
+        0x00, 0x00, 0x80, 0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x02, 0x00, 0x00,
+    ],
+    // ']'
+    [
+        0x00, 0x00, 0x78, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x78, 0x00, 0x00,
+    ],
+    // '^'
+    [
+        0x00, 0x00, 0x10, 0x28, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // '_'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0x00,
+    ],
+    // '`'
+    [
+        0x00, 0x10, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // 'a'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x02, 0x3E, 0x42, 0x46, 0x3A, 0x00, 0x00,
+    ],
+    // 'b'
+    [
+        0x00, 0x00, 0x40, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x62, 0x5C, 0x00, 0x00,
+    ],
+    // 'c'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'd'
+    [
+        0x00, 0x00, 0x02, 0x02, 0x02, 0x3A, 0x46, 0x42, 0x42, 0x46, 0x3A, 0x00, 0x00,
+    ],
+    // 'e'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x7E, 0x40, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'f'
+    [
+        0x00, 0x00, 0x1C, 0x22, 0x20, 0x20, 0x7C, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00,
+    ],
+    // 'g'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x44, 0x44, 0x38, 0x40, 0x3C, 0x42, 0x3C,
+    ],
+    // 'h'
+    [
+        0x00, 0x00, 0x40, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x42, 0x42, 0x00, 0x00,
+    ],
+    // 'i'
+    [
+        0x00, 0x00, 0x00, 0x10, 0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00,
+    ],
+    // 'j'
+    [
+        0x00, 0x00, 0x00, 0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x44, 0x44, 0x38,
+    ],
+    // 'k'
+    [
+        0x00, 0x00, 0x40, 0x40, 0x40, 0x44, 0x48, 0x70, 0x48, 0x44, 0x42, 0x00, 0x00,
+    ],
+    // 'l'
+    [
+        0x00, 0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00,
+    ],
+    // 'm'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0xEC, 0x92, 0x92, 0x92, 0x92, 0x82, 0x00, 0x00,
+    ],
+    // 'n'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x5C, 0x62, 0x42, 0x42, 0x42, 0x42, 0x00, 0x00,
+    ],
+    // 'o'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 'p'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x5C, 0x62, 0x42, 0x62, 0x5C, 0x40, 0x40, 0x40,
+    ],
+    // 'q'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x46, 0x42, 0x46, 0x3A, 0x02, 0x02, 0x02,
+    ],
+    // 'r'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x5C, 0x22, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00,
+    ],
+    // 's'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x30, 0x0C, 0x42, 0x3C, 0x00, 0x00,
+    ],
+    // 't'
+    [
+        0x00, 0x00, 0x00, 0x20, 0x20, 0x7C, 0x20, 0x20, 0x20, 0x22, 0x1C, 0x00, 0x00,
+    ],
+    // 'u'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x44, 0x44, 0x44, 0x44, 0x44, 0x3A, 0x00, 0x00,
+    ],
+    // 'v'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x44, 0x44, 0x44, 0x28, 0x28, 0x10, 0x00, 0x00,
+    ],
+    // 'w'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x92, 0x92, 0xAA, 0x44, 0x00, 0x00,
+    ],
+    // 'x'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x42, 0x24, 0x18, 0x18, 0x24, 0x42, 0x00, 0x00,
+    ],
+    // 'y'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x42, 0x42, 0x42, 0x46, 0x3A, 0x02, 0x42, 0x3C,
+    ],
+    // 'z'
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x7E, 0x04, 0x08, 0x10, 0x20, 0x7E, 0x00, 0x00,
+    ],
+    // '{'
+    [
+        0x00, 0x00, 0x0E, 0x10, 0x10, 0x08, 0x30, 0x08, 0x10, 0x10, 0x0E, 0x00, 0x00,
+    ],
+    // '|'
+    [
+        0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00,
+    ],
+    // '}'
+    [
+        0x00, 0x00, 0x70, 0x08, 0x08, 0x10, 0x0C, 0x10, 0x08, 0x08, 0x70, 0x00, 0x00,
+    ],
+    // '~'
+    [
+        0x00, 0x00, 0x24, 0x54, 0x48, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+];
+
+// symbols used by appcui's `SpecialChar` that are neither box drawing nor block elements
+static SYMBOLS: &[(char, [u8; HEIGHT])] = &[
+    (
+        '↑',
+        [
+            0x00, 0x00, 0x10, 0x38, 0x54, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '↓',
+        [
+            0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x54, 0x38, 0x10, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '←',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x20, 0x40, 0xFE, 0x40, 0x20, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '→',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x08, 0x04, 0xFE, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '↕',
+        [
+            0x00, 0x10, 0x38, 0x54, 0x10, 0x10, 0x10, 0x10, 0x10, 0x54, 0x38, 0x10, 0x00,
+        ],
+    ),
+    (
+        '↔',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x28, 0x44, 0xFE, 0x44, 0x28, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '■',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x7E, 0x7E, 0x7E, 0x7E, 0x7E, 0x7E, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '▲',
+        [
+            0x00, 0x00, 0x00, 0x10, 0x10, 0x38, 0x38, 0x7C, 0x7C, 0xFE, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '▼',
+        [
+            0x00, 0x00, 0x00, 0xFE, 0x7C, 0x7C, 0x38, 0x38, 0x10, 0x10, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '◄',
+        [
+            0x00, 0x00, 0x00, 0x06, 0x1E, 0x7E, 0xFE, 0x7E, 0x1E, 0x06, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '►',
+        [
+            0x00, 0x00, 0x00, 0xC0, 0xF0, 0xFC, 0xFE, 0xFC, 0xF0, 0xC0, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '●',
+        [
+            0x00, 0x00, 0x00, 0x38, 0x7C, 0xFE, 0xFE, 0xFE, 0x7C, 0x38, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '○',
+        [
+            0x00, 0x00, 0x00, 0x38, 0x44, 0x82, 0x82, 0x82, 0x44, 0x38, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '√',
+        [
+            0x00, 0x00, 0x07, 0x04, 0x04, 0x04, 0x84, 0x48, 0x28, 0x18, 0x08, 0x00, 0x00,
+        ],
+    ),
+    (
+        '≡',
+        [
+            0x00, 0x00, 0x00, 0x00, 0xFE, 0x00, 0xFE, 0x00, 0xFE, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '⁞',
+        [
+            0x00, 0x00, 0x10, 0x00, 0x10, 0x00, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '…',
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x92, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '▶',
+        [
+            0x00, 0x00, 0x00, 0xC0, 0xF0, 0xFC, 0xFE, 0xFC, 0xF0, 0xC0, 0x00, 0x00, 0x00,
+        ],
+    ),
+    (
+        '◀',
+        [
+            0x00, 0x00, 0x00, 0x06, 0x1E, 0x7E, 0xFE, 0x7E, 0x1E, 0x06, 0x00, 0x00, 0x00,
+        ],
+    ),
+];
+
+fn hline(glyph: &mut Glyph, y: usize, x0: usize, x1: usize) {
+    for x in x0..=x1 {
+        glyph[y] |= 0x80 >> x;
+    }
+}
+
+fn vline(glyph: &mut Glyph, x: usize, y0: usize, y1: usize) {
+    for row in glyph[y0..=y1].iter_mut() {
+        *row |= 0x80 >> x;
+    }
+}
+
+fn fill(glyph: &mut Glyph, x0: usize, x1: usize, y0: usize, y1: usize) {
+    for y in y0..y1 {
+        for x in x0..x1 {
+            glyph[y] |= 0x80 >> x;
+        }
+    }
+}
+
+// box drawing characters are built from their arms (see `box_join`)
+fn box_glyph(arms: [u8; 4]) -> Glyph {
+    let mut g = [0; HEIGHT];
+    let double = |dir: usize| arms[dir] == D;
+    // light and heavy arms stop at the outer line of a perpendicular double arm
+    let h_double = double(LEFT) || double(RIGHT);
+    let v_double = double(UP) || double(DOWN);
+    // arms coming from above or from the left also cover the thickness of a perpendicular heavy arm
+    let h_heavy = (arms[LEFT] == H || arms[RIGHT] == H) as usize;
+    let v_heavy = (arms[UP] == H || arms[DOWN] == H) as usize;
+    for dir in [UP, RIGHT, DOWN, LEFT] {
+        let weight = arms[dir];
+        if weight == 0 {
+            continue;
+        }
+        if weight == D {
+            // each of the two parallel lines stops early when it is on the side of another double arm
+            let (near, far) = (CX - 2, CX + 2);
+            match dir {
+                UP => {
+                    vline(&mut g, near, 0, if double(LEFT) { CY - 2 } else { CY + 2 });
+                    vline(&mut g, far, 0, if double(RIGHT) { CY - 2 } else { CY + 2 });
+                }
+                DOWN => {
+                    vline(
+                        &mut g,
+                        near,
+                        if double(LEFT) { CY + 2 } else { CY - 2 },
+                        HEIGHT - 1,
+                    );
+                    vline(
+                        &mut g,
+                        far,
+                        if double(RIGHT) { CY + 2 } else { CY - 2 },
+                        HEIGHT - 1,
+                    );
+                }
+                LEFT => {
+                    hline(&mut g, CY - 2, 0, if double(UP) { near } else { far });
+                    hline(&mut g, CY + 2, 0, if double(DOWN) { near } else { far });
+                }
+                _ => {
+                    hline(
+                        &mut g,
+                        CY - 2,
+                        if double(UP) { far } else { near },
+                        WIDTH - 1,
+                    );
+                    hline(
+                        &mut g,
+                        CY + 2,
+                        if double(DOWN) { far } else { near },
+                        WIDTH - 1,
+                    );
+                }
+            }
+            continue;
+        }
+        let thickness = if weight == H { 2 } else { 1 };
+        for t in 0..thickness {
+            match dir {
+                UP => vline(
+                    &mut g,
+                    CX + t,
+                    0,
+                    if h_double { CY - 2 } else { CY + h_heavy },
+                ),
+                DOWN => vline(
+                    &mut g,
+                    CX + t,
+                    if h_double { CY + 2 } else { CY },
+                    HEIGHT - 1,
+                ),
+                LEFT => hline(
+                    &mut g,
+                    CY + t,
+                    0,
+                    if v_double { CX - 2 } else { CX + v_heavy },
+                ),
+                _ => hline(
+                    &mut g,
+                    CY + t,
+                    if v_double { CX + 2 } else { CX },
+                    WIDTH - 1,
+                ),
+            }
+        }
+    }
+    g
+}
+
+// block elements (U+2580..=U+259F)
+fn block_glyph(ch: char) -> Option<Glyph> {
+    let mut g = [0; HEIGHT];
+    let (half_w, half_h) = (WIDTH / 2, HEIGHT / 2);
+    let eighth_h = |n: usize| (HEIGHT * n + 4) / 8;
+    match ch {
+        '▀' => fill(&mut g, 0, WIDTH, 0, half_h),
+        '▁'..='█' => {
+            let n = ch as usize - '▀' as usize;
+            fill(&mut g, 0, WIDTH, HEIGHT - eighth_h(n), HEIGHT);
+        }
+        '▉'..='▏' => {
+            let n = 8 - (ch as usize - '█' as usize);
+            fill(&mut g, 0, n, 0, HEIGHT);
+        }
+        '▐' => fill(&mut g, half_w, WIDTH, 0, HEIGHT),
+        '░' | '▒' | '▓' => {
+            for (y, row) in g.iter_mut().enumerate() {
+                for x in 0..WIDTH {
+                    let on = match ch {
+                        '░' => (x + 2 * (y % 2)) % 4 == 0,
+                        '▒' => (x + y) % 2 == 0,
+                        _ => (x + 2 * (y % 2)) % 4 != 0,
+                    };
+                    if on {
+                        *row |= 0x80 >> x;
+                    }
+                }
+            }
+        }
+        '▔' => fill(&mut g, 0, WIDTH, 0, eighth_h(1)),
+        '▕' => fill(&mut g, WIDTH - 1, WIDTH, 0, HEIGHT),
+        '▖'..='▟' => {
+            // quadrants as [upper left, upper right, lower left, lower right]
+            let quadrants = match ch {
+                '▖' => [false, false, true, false],
+                '▗' => [false, false, false, true],
+                '▘' => [true, false, false, false],
+                '▙' => [true, false, true, true],
+                '▚' => [true, false, false, true],
+                '▛' => [true, true, true, false],
+                '▜' => [true, true, false, true],
+                '▝' => [false, true, false, false],
+                '▞' => [false, true, true, false],
+                _ => [false, true, true, true],
+            };
+            for (i, on) in quadrants.iter().enumerate() {
+                if *on {
+                    let (x0, x1) = if i % 2 == 0 {
+                        (0, half_w)
+                    } else {
+                        (half_w, WIDTH)
+                    };
+                    let (y0, y1) = if i < 2 { (0, half_h) } else { (half_h, HEIGHT) };
+                    fill(&mut g, x0, x1, y0, y1);
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(g)
+}
+
+// empty rectangle drawn for characters the font does not cover
+fn missing_glyph() -> Glyph {
+    let mut g = [0; HEIGHT];
+    hline(&mut g, 2, 1, WIDTH - 2);
+    hline(&mut g, HEIGHT - 3, 1, WIDTH - 2);
+    vline(&mut g, 1, 2, HEIGHT - 3);
+    vline(&mut g, WIDTH - 2, 2, HEIGHT - 3);
+    g
+}
+
+pub(crate) fn glyph(ch: char) -> Glyph {
+    match ch {
+        '\0' => [0; HEIGHT],
+        ' '..='~' => ASCII[ch as usize - ' ' as usize],
+        _ => box_join::arms(ch)
+            .map(box_glyph)
+            .or_else(|| block_glyph(ch))
+            .or_else(|| SYMBOLS.iter().find(|(c, _)| *c == ch).map(|(_, g)| *g))
+            .unwrap_or_else(missing_glyph),
+    }
+}
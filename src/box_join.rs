@@ -0,0 +1,202 @@
+use appcui::prelude::*;
+
+// weight of an arm of a box drawing character
+const N: u8 = 0; // no line
+pub(crate) const L: u8 = 1; // light (single)
+pub(crate) const H: u8 = 2; // heavy (thick)
+pub(crate) const D: u8 = 3; // double
+
+pub(crate) const UP: usize = 0;
+pub(crate) const RIGHT: usize = 1;
+pub(crate) const DOWN: usize = 2;
+pub(crate) const LEFT: usize = 3;
+
+// arms are stored as [up, right, down, left]
+static BOX_CHARS: &[(char, [u8; 4])] = &[
+    // light and heavy lines
+    ('─', [N, L, N, L]),
+    ('━', [N, H, N, H]),
+    ('│', [L, N, L, N]),
+    ('┃', [H, N, H, N]),
+    ('┌', [N, L, L, N]),
+    ('┍', [N, H, L, N]),
+    ('┎', [N, L, H, N]),
+    ('┏', [N, H, H, N]),
+    ('┐', [N, N, L, L]),
+    ('┑', [N, N, L, H]),
+    ('┒', [N, N, H, L]),
+    ('┓', [N, N, H, H]),
+    ('└', [L, L, N, N]),
+    ('┕', [L, H, N, N]),
+    ('┖', [H, L, N, N]),
+    ('┗', [H, H, N, N]),
+    ('┘', [L, N, N, L]),
+    ('┙', [L, N, N, H]),
+    ('┚', [H, N, N, L]),
+    ('┛', [H, N, N, H]),
+    ('├', [L, L, L, N]),
+    ('┝', [L, H, L, N]),
+    ('┞', [H, L, L, N]),
+    ('┟', [L, L, H, N]),
+    ('┠', [H, L, H, N]),
+    ('┡', [H, H, L, N]),
+    ('┢', [L, H, H, N]),
+    ('┣', [H, H, H, N]),
+    ('┤', [L, N, L, L]),
+    ('┥', [L, N, L, H]),
+    ('┦', [H, N, L, L]),
+    ('┧', [L, N, H, L]),
+    ('┨', [H, N, H, L]),
+    ('┩', [H, N, L, H]),
+    ('┪', [L, N, H, H]),
+    ('┫', [H, N, H, H]),
+    ('┬', [N, L, L, L]),
+    ('┭', [N, L, L, H]),
+    ('┮', [N, H, L, L]),
+    ('┯', [N, H, L, H]),
+    ('┰', [N, L, H, L]),
+    ('┱', [N, L, H, H]),
+    ('┲', [N, H, H, L]),
+    ('┳', [N, H, H, H]),
+    ('┴', [L, L, N, L]),
+    ('┵', [L, L, N, H]),
+    ('┶', [L, H, N, L]),
+    ('┷', [L, H, N, H]),
+    ('┸', [H, L, N, L]),
+    ('┹', [H, L, N, H]),
+    ('┺', [H, H, N, L]),
+    ('┻', [H, H, N, H]),
+    ('┼', [L, L, L, L]),
+    ('┽', [L, L, L, H]),
+    ('┾', [L, H, L, L]),
+    ('┿', [L, H, L, H]),
+    ('╀', [H, L, L, L]),
+    ('╁', [L, L, H, L]),
+    ('╂', [H, L, H, L]),
+    ('╃', [H, L, L, H]),
+    ('╄', [H, H, L, L]),
+    ('╅', [L, L, H, H]),
+    ('╆', [L, H, H, L]),
+    ('╇', [H, H, L, H]),
+    ('╈', [L, H, H, H]),
+    ('╉', [H, L, H, H]),
+    ('╊', [H, H, H, L]),
+    ('╋', [H, H, H, H]),
+    // double and mixed single/double lines
+    ('═', [N, D, N, D]),
+    ('║', [D, N, D, N]),
+    ('╒', [N, D, L, N]),
+    ('╓', [N, L, D, N]),
+    ('╔', [N, D, D, N]),
+    ('╕', [N, N, L, D]),
+    ('╖', [N, N, D, L]),
+    ('╗', [N, N, D, D]),
+    ('╘', [L, D, N, N]),
+    ('╙', [D, L, N, N]),
+    ('╚', [D, D, N, N]),
+    ('╛', [L, N, N, D]),
+    ('╜', [D, N, N, L]),
+    ('╝', [D, N, N, D]),
+    ('╞', [L, D, L, N]),
+    ('╟', [D, L, D, N]),
+    ('╠', [D, D, D, N]),
+    ('╡', [L, N, L, D]),
+    ('╢', [D, N, D, L]),
+    ('╣', [D, N, D, D]),
+    ('╤', [N, D, L, D]),
+    ('╥', [N, L, D, L]),
+    ('╦', [N, D, D, D]),
+    ('╧', [L, D, N, D]),
+    ('╨', [D, L, N, L]),
+    ('╩', [D, D, N, D]),
+    ('╪', [L, D, L, D]),
+    ('╫', [D, L, D, L]),
+    ('╬', [D, D, D, D]),
+    // half lines
+    ('╴', [N, N, N, L]),
+    ('╵', [L, N, N, N]),
+    ('╶', [N, L, N, N]),
+    ('╷', [N, N, L, N]),
+    ('╸', [N, N, N, H]),
+    ('╹', [H, N, N, N]),
+    ('╺', [N, H, N, N]),
+    ('╻', [N, N, H, N]),
+    ('╼', [N, H, N, L]),
+    ('╽', [L, N, H, N]),
+    ('╾', [N, L, N, H]),
+    ('╿', [H, N, L, N]),
+    // rounded corners (after the square ones so that lookups by arms return square corners)
+    ('╭', [N, L, L, N]),
+    ('╮', [N, N, L, L]),
+    ('╯', [L, N, N, L]),
+    ('╰', [L, L, N, N]),
+];
+
+pub(crate) fn arms(ch: char) -> Option<[u8; 4]> {
+    BOX_CHARS.iter().find(|(c, _)| *c == ch).map(|(_, a)| *a)
+}
+
+pub(crate) fn char_with_arms(arms: [u8; 4]) -> Option<char> {
+    BOX_CHARS.iter().find(|(_, a)| *a == arms).map(|(c, _)| *c)
+}
+
+pub(crate) fn opposite(dir: usize) -> usize {
+    (dir + 2) % 4
+}
+
+fn neighbour(x: i32, y: i32, dir: usize) -> (i32, i32) {
+    match dir {
+        UP => (x, y - 1),
+        RIGHT => (x + 1, y),
+        DOWN => (x, y + 1),
+        _ => (x - 1, y),
+    }
+}
+
+// Merges the box drawing characters written inside `rect` with the ones that were there before
+// (`before` holds a copy of the `rect` area taken prior to drawing). An arm of the old glyph is kept
+// only if the neighbouring cell in that direction still connects back to it.
+pub(crate) fn join(surface: &mut Surface, before: &Surface, rect: Rect) {
+    let mut result = Vec::new();
+    for y in rect.top()..=rect.bottom() {
+        for x in rect.left()..=rect.right() {
+            let Some(new_ch) = surface.char(x, y).copied() else {
+                continue;
+            };
+            let Some(old_ch) = before.char(x - rect.left(), y - rect.top()) else {
+                continue;
+            };
+            if new_ch.code == old_ch.code {
+                continue;
+            }
+            let (Some(new_arms), Some(old_arms)) = (arms(new_ch.code), arms(old_ch.code)) else {
+                continue;
+            };
+            let mut merged = new_arms;
+            for dir in [UP, RIGHT, DOWN, LEFT] {
+                if merged[dir] != N || old_arms[dir] == N {
+                    continue;
+                }
+                let (nx, ny) = neighbour(x, y, dir);
+                let connects = surface
+                    .char(nx, ny)
+                    .and_then(|c| arms(c.code))
+                    .is_some_and(|a| a[opposite(dir)] != N);
+                if connects {
+                    merged[dir] = old_arms[dir];
+                }
+            }
+            // nothing was added - the new glyph stays (a rounded corner has the same arms as a square one)
+            if merged == new_arms {
+                continue;
+            }
+            // some combinations (e.g. double and thick) have no glyph - keep the new one in that case
+            if let Some(code) = char_with_arms(merged) {
+                result.push((x, y, Character { code, ..new_ch }));
+            }
+        }
+    }
+    for (x, y, ch) in result {
+        surface.write_char(x, y, ch);
+    }
+}
@@ -0,0 +1,129 @@
+use appcui::prelude::*;
+
+#[derive(Copy, Clone)]
+pub struct CanvasSizeOptions {
+    pub width: u32,
+    pub height: u32,
+    // column and row (0..=2) of the anchor: the part of the painting that keeps its place
+    pub anchor: (u32, u32),
+    // used for the new area of the bottom layer
+    pub fill: Character,
+}
+
+impl CanvasSizeOptions {
+    // the new canvas, in the coordinates of the current one
+    pub fn rect(&self, current: Size) -> Rect {
+        let dx = (self.width as i32 - current.width as i32) * self.anchor.0 as i32 / 2;
+        let dy = (self.height as i32 - current.height as i32) * self.anchor.1 as i32 / 2;
+        Rect::with_point_and_size(Point::new(-dx, -dy), Size::new(self.width, self.height))
+    }
+}
+
+fn is_blank(ch: &Character) -> bool {
+    matches!(ch.code, ' ' | '\0') && matches!(ch.background, Color::Black | Color::Transparent)
+}
+
+// smallest rectangle that contains every non blank cell
+pub fn content_bounds(surface: &Surface) -> Option<Rect> {
+    let size = surface.size();
+    let mut bounds: Option<(i32, i32, i32, i32)> = None;
+    for y in 0..size.height as i32 {
+        for x in 0..size.width as i32 {
+            if surface.char(x, y).is_some_and(|ch| !is_blank(ch)) {
+                bounds = Some(match bounds {
+                    Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x), b.max(y)),
+                    None => (x, y, x, y),
+                });
+            }
+        }
+    }
+    bounds.map(|(l, t, r, b)| Rect::new(l, t, r, b))
+}
+
+#[ModalWindow(events = ButtonEvents, response = CanvasSizeOptions)]
+pub struct CanvasSizeDialog {
+    width: Handle<NumericSelector<u32>>,
+    height: Handle<NumericSelector<u32>>,
+    // the 3x3 anchor grid (row by row)
+    anchors: [Handle<RadioBox>; 9],
+    fill_char: Handle<CharPicker>,
+    fill_fore: Handle<ColorPicker>,
+    fill_back: Handle<ColorPicker>,
+    resize: Handle<Button>,
+}
+
+impl CanvasSizeDialog {
+    pub fn new(current: Size) -> Self {
+        let mut w = Self {
+            base: ModalWindow::new("Canvas size", layout!("a:c,w:50,h:17"), window::Flags::None),
+            width: Handle::None,
+            height: Handle::None,
+            anchors: [Handle::None; 9],
+            fill_char: Handle::None,
+            fill_fore: Handle::None,
+            fill_back: Handle::None,
+            resize: Handle::None,
+        };
+        w.add(label!("'Width:',x:1,y:1,w:10,h:1"));
+        w.width = w.add(NumericSelector::new(
+            current.width,
+            1,
+            1000,
+            1,
+            layout!("x:12,y:1,w:34"),
+            numericselector::Flags::None,
+        ));
+        w.add(label!("'Height:',x:1,y:3,w:10,h:1"));
+        w.height = w.add(NumericSelector::new(
+            current.height,
+            1,
+            1000,
+            1,
+            layout!("x:12,y:3,w:34"),
+            numericselector::Flags::None,
+        ));
+        w.add(label!("'Anchor:',x:1,y:5,w:10,h:1"));
+        for index in 0..9 {
+            let (column, row) = ((index % 3) as i32, (index / 3) as i32);
+            let layout = Layout::absolute(12 + column * 4, 5 + row, 3, 1);
+            // the center is selected by default
+            w.anchors[index] = w.add(RadioBox::new("", layout, index == 4));
+        }
+        w.add(label!("'Fill:',x:1,y:9,w:10,h:1"));
+        w.fill_char = w.add(CharPicker::new(Some(' '), layout!("x:12,y:9,w:34")));
+        w.add(label!("'Fore:',x:1,y:11,w:10,h:1"));
+        w.fill_fore = w.add(ColorPicker::new(Color::White, layout!("x:12,y:11,w:14")));
+        w.add(label!("'Back:',x:28,y:11,w:5,h:1"));
+        w.fill_back = w.add(ColorPicker::new(Color::Black, layout!("x:34,y:11,w:12")));
+        w.resize = w.add(button!("&Resize,x:11,y:13,w:12"));
+        w.add(button!("&Cancel,x:25,y:13,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for CanvasSizeDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.resize {
+            let anchor = self
+                .anchors
+                .iter()
+                .position(|h| self.control(*h).is_some_and(|r| r.is_selected()))
+                .unwrap_or(4) as u32;
+            let options = CanvasSizeOptions {
+                width: self.control(self.width).unwrap().value(),
+                height: self.control(self.height).unwrap().value(),
+                anchor: (anchor % 3, anchor / 3),
+                fill: Character::new(
+                    self.control(self.fill_char).unwrap().char().unwrap_or(' '),
+                    self.control(self.fill_fore).unwrap().color(),
+                    self.control(self.fill_back).unwrap().color(),
+                    CharFlags::None,
+                ),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
@@ -0,0 +1,121 @@
+use appcui::prelude::*;
+
+// RGB values of the 16 console colors (in the same order as appcui's `Color` enum)
+pub const PALETTE: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), // Black
+    (0x00, 0x00, 0x80), // DarkBlue
+    (0x00, 0x80, 0x00), // DarkGreen
+    (0x00, 0x80, 0x80), // Teal
+    (0x80, 0x00, 0x00), // DarkRed
+    (0x80, 0x00, 0x80), // Magenta
+    (0x80, 0x80, 0x00), // Olive
+    (0xC0, 0xC0, 0xC0), // Silver
+    (0x80, 0x80, 0x80), // Gray
+    (0x00, 0x00, 0xFF), // Blue
+    (0x00, 0xFF, 0x00), // Green
+    (0x00, 0xFF, 0xFF), // Aqua
+    (0xFF, 0x00, 0x00), // Red
+    (0xFF, 0x00, 0xFF), // Pink
+    (0xFF, 0xFF, 0x00), // Yellow
+    (0xFF, 0xFF, 0xFF), // White
+];
+
+const NAMED: [Color; 16] = [
+    Color::Black,
+    Color::DarkBlue,
+    Color::DarkGreen,
+    Color::Teal,
+    Color::DarkRed,
+    Color::Magenta,
+    Color::Olive,
+    Color::Silver,
+    Color::Gray,
+    Color::Blue,
+    Color::Green,
+    Color::Aqua,
+    Color::Red,
+    Color::Pink,
+    Color::Yellow,
+    Color::White,
+];
+
+// index of a named color in `PALETTE` (None for RGB and transparent colors)
+pub fn index(color: Color) -> Option<usize> {
+    NAMED.iter().position(|c| *c == color)
+}
+
+pub fn named(index: usize) -> Color {
+    NAMED[index & 15]
+}
+
+pub fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    match color {
+        Color::Transparent => None,
+        Color::RGB(r, g, b) => Some((r, g, b)),
+        _ => index(color).map(|i| PALETTE[i]),
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+// closest of the 16 console colors
+pub fn nearest(rgb: (u8, u8, u8)) -> Color {
+    let i = (0..16)
+        .min_by_key(|i| distance(PALETTE[*i], rgb))
+        .unwrap_or(0);
+    NAMED[i]
+}
+
+// appcui stores the color bits as (blue, green, red) while ANSI uses (red, green, blue)
+pub fn to_ansi_index(index: usize) -> u8 {
+    let i = index as u8;
+    (i & 8) | ((i & 1) << 2) | (i & 2) | ((i & 4) >> 2)
+}
+
+pub fn from_ansi_index(index: u8) -> usize {
+    to_ansi_index(index as usize) as usize
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// closest entry of the xterm 256 color palette (only the 6x6x6 cube and the gray ramp are considered)
+pub fn to_xterm256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| {
+        (0..6)
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_idx = (avg.saturating_sub(3) / 10).min(23) as u8;
+    let gray_value = 8 + gray_idx * 10;
+    if distance((gray_value, gray_value, gray_value), rgb) < distance(cube, rgb) {
+        232 + gray_idx
+    } else {
+        16 + (36 * r + 6 * g + b) as u8
+    }
+}
+
+pub fn from_xterm256(index: u8) -> Color {
+    match index {
+        0..=15 => named(from_ansi_index(index)),
+        16..=231 => {
+            let i = (index - 16) as usize;
+            Color::RGB(
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[(i / 6) % 6],
+                CUBE_LEVELS[i % 6],
+            )
+        }
+        _ => {
+            let v = 8 + (index - 232) * 10;
+            Color::RGB(v, v, v)
+        }
+    }
+}
@@ -1,9 +1,17 @@
 use appcui::prelude::*;
 
+use crate::box_join;
+use crate::layer;
+use crate::layer::Cells;
+use crate::transform;
+use crate::transform::Transform;
+
 pub struct RectangleObject {
     pub fore: Color,
     pub back: Color,
+    pub flags: CharFlags,
     pub line_type: LineType,
+    pub auto_join: bool,
 }
 
 impl Default for RectangleObject {
@@ -11,7 +19,9 @@ impl Default for RectangleObject {
         Self {
             fore: Color::White,
             back: Color::Black,
+            flags: CharFlags::None,
             line_type: LineType::Single,
+            auto_join: true,
         }
     }
 }
@@ -34,19 +44,74 @@ impl Default for FillRectangleObject {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LineDirection {
+    Horizontal,
+    Vertical,
+    Free,
+}
+
 pub struct LineObject {
     pub fore: Color,
     pub back: Color,
+    pub flags: CharFlags,
     pub line_type: LineType,
-    pub vertical: bool,
+    pub direction: LineDirection,
+    pub fixed_char: Option<char>,
+    pub auto_join: bool,
 }
 impl Default for LineObject {
     fn default() -> Self {
         Self {
             fore: Color::White,
             back: Color::Black,
+            flags: CharFlags::None,
             line_type: LineType::Single,
-            vertical: false,
+            direction: LineDirection::Horizontal,
+            fixed_char: None,
+            auto_join: true,
+        }
+    }
+}
+impl LineObject {
+    // horizontal, vertical, rising ('/') and falling ('\') glyphs for a line type
+    fn glyphs(&self) -> [char; 4] {
+        match self.line_type {
+            LineType::Single | LineType::SingleRound => ['─', '│', '╱', '╲'],
+            LineType::Double => ['═', '║', '╱', '╲'],
+            LineType::SingleThick => ['━', '┃', '╱', '╲'],
+            LineType::Border => ['█', '█', '█', '█'],
+            LineType::Ascii | LineType::AsciiRound => ['-', '|', '/', '\\'],
+            LineType::Braille => ['\u{2836}', '\u{28FF}', '\u{280C}', '\u{2821}'],
+        }
+    }
+    fn draw(&self, surface: &mut Surface, from: Point, to: Point) {
+        let glyphs = self.glyphs();
+        let points = line_points(from, to);
+        for (i, p) in points.iter().enumerate() {
+            let code = if let Some(ch) = self.fixed_char {
+                ch
+            } else {
+                // pick the glyph based on the step towards the next cell (or from the previous one for the last cell)
+                let (dx, dy) = if i + 1 < points.len() {
+                    (points[i + 1].x - p.x, points[i + 1].y - p.y)
+                } else if i > 0 {
+                    (p.x - points[i - 1].x, p.y - points[i - 1].y)
+                } else {
+                    (1, 0)
+                };
+                match (dx, dy) {
+                    (_, 0) => glyphs[0],
+                    (0, _) => glyphs[1],
+                    _ if dx * dy < 0 => glyphs[2],
+                    _ => glyphs[3],
+                }
+            };
+            surface.write_char(
+                p.x,
+                p.y,
+                Character::new(code, self.fore, self.back, self.flags),
+            );
         }
     }
 }
@@ -70,13 +135,55 @@ impl Default for TextObject {
 
 pub struct SelectionObject {
     img: Option<Surface>,
-    start_point: Point,
+    // the area the image was taken from (it is cleared when the image is placed)
+    source: Rect,
+    // when the selection is resized, keep the border cells and stretch only the inside of the image
+    pub nine_slice: bool,
 }
 impl Default for SelectionObject {
     fn default() -> Self {
         Self {
             img: None,
-            start_point: Point::new(0, 0),
+            source: Rect::new(0, 0, 0, 0),
+            nine_slice: false,
+        }
+    }
+}
+
+impl SelectionObject {
+    // Moves the image to `rect` on the layer. The cells it was taken from are emptied (on the bottom layer
+    // they become spaces that keep their colors) and the transparent cells of the image leave the painting
+    // below them visible.
+    pub fn place(&self, cells: &mut Cells, rect: Rect, bottom_layer: bool) {
+        let Some(img) = &self.img else {
+            return;
+        };
+        if bottom_layer {
+            let space =
+                Character::new(' ', Color::Transparent, Color::Transparent, CharFlags::None);
+            for y in self.source.top()..=self.source.bottom() {
+                for x in self.source.left()..=self.source.right() {
+                    cells.write_char(x, y, space);
+                }
+            }
+        } else {
+            cells.set_rect(self.source, layer::EMPTY);
+        }
+        // the image is stretched to the (resized) selection
+        let size = Size::new(rect.width(), rect.height());
+        let scaled;
+        let img = if img.size() == size {
+            img
+        } else {
+            scaled = transform::scale(img, size, self.nine_slice);
+            &scaled
+        };
+        for y in 0..size.height as i32 {
+            for x in 0..size.width as i32 {
+                if let Some(ch) = img.char(x, y) {
+                    cells.write_char(rect.left() + x, rect.top() + y, *ch);
+                }
+            }
         }
     }
 }
@@ -84,6 +191,398 @@ impl Default for SelectionObject {
 #[derive(Default)]
 pub struct MoveObject {}
 
+pub struct PencilObject {
+    pub fore: Color,
+    pub back: Color,
+    pub ch: char,
+    pub flags: CharFlags,
+    last_point: Option<Point>,
+    // the cells the stroke went over since it started
+    stroke_area: Option<Rect>,
+}
+impl Default for PencilObject {
+    fn default() -> Self {
+        Self {
+            fore: Color::White,
+            back: Color::Black,
+            ch: '*',
+            flags: CharFlags::None,
+            last_point: None,
+            stroke_area: None,
+        }
+    }
+}
+
+pub struct EraserObject {
+    pub width: u32,
+    pub height: u32,
+    pub blank: Character,
+    last_point: Option<Point>,
+    // the cells the stroke went over since it started
+    stroke_area: Option<Rect>,
+}
+impl Default for EraserObject {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            blank: Character::new(' ', Color::White, Color::Black, CharFlags::None),
+            last_point: None,
+            stroke_area: None,
+        }
+    }
+}
+impl EraserObject {
+    // the brush is centered on the cursor
+    fn brush_rect(&self, point: Point) -> Rect {
+        Rect::with_size(
+            point.x - (self.width as i32 - 1) / 2,
+            point.y - (self.height as i32 - 1) / 2,
+            self.width as u16,
+            self.height as u16,
+        )
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum FillMatch {
+    #[VariantInfo(name = "Same char", description = "Cells with the same character")]
+    Char,
+    #[VariantInfo(
+        name = "Same background",
+        description = "Cells with the same background color"
+    )]
+    Background,
+    #[VariantInfo(
+        name = "Same foreground",
+        description = "Cells with the same foreground color"
+    )]
+    Foreground,
+    #[VariantInfo(
+        name = "Exact",
+        description = "Cells with the same character, colors and flags"
+    )]
+    Exact,
+}
+impl FillMatch {
+    fn matches(&self, a: &Character, b: &Character) -> bool {
+        match self {
+            FillMatch::Char => a.code == b.code,
+            FillMatch::Background => a.background == b.background,
+            FillMatch::Foreground => a.foreground == b.foreground,
+            FillMatch::Exact => a == b,
+        }
+    }
+}
+
+pub struct FloodFillObject {
+    pub fore: Color,
+    pub back: Color,
+    pub ch: char,
+    pub flags: CharFlags,
+    pub match_mode: FillMatch,
+    pub diagonal: bool,
+}
+impl Default for FloodFillObject {
+    fn default() -> Self {
+        Self {
+            fore: Color::White,
+            back: Color::Black,
+            ch: ' ',
+            flags: CharFlags::None,
+            match_mode: FillMatch::Exact,
+            diagonal: false,
+        }
+    }
+}
+impl FloodFillObject {
+    // iterative (explicit stack) so that large canvases do not overflow the call stack; returns the
+    // smallest rectangle that contains the filled cells
+    fn fill(&self, cells: &mut Cells, start: Point) -> Option<Rect> {
+        let seed = cells.char(start.x, start.y).copied()?;
+        let new_char = Character::new(self.ch, self.fore, self.back, self.flags);
+        let size = cells.size();
+        let (w, h) = (size.width as i32, size.height as i32);
+        let mut visited = vec![false; (size.width * size.height) as usize];
+        let mut stack = vec![start];
+        visited[(start.y * w + start.x) as usize] = true;
+        let mut area = Rect::new(start.x, start.y, start.x, start.y);
+        while let Some(p) = stack.pop() {
+            cells.write_char(p.x, p.y, new_char);
+            area = layer::union(area, Rect::new(p.x, p.y, p.x, p.y));
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if (dx == 0 && dy == 0) || (!self.diagonal && dx != 0 && dy != 0) {
+                        continue;
+                    }
+                    let (x, y) = (p.x + dx, p.y + dy);
+                    if x < 0 || y < 0 || x >= w || y >= h {
+                        continue;
+                    }
+                    let idx = (y * w + x) as usize;
+                    if visited[idx] {
+                        continue;
+                    }
+                    if let Some(ch) = cells.char(x, y) {
+                        if self.match_mode.matches(ch, &seed) {
+                            visited[idx] = true;
+                            stack.push(Point::new(x, y));
+                        }
+                    }
+                }
+            }
+        }
+        Some(area)
+    }
+}
+
+pub struct EllipseObject {
+    pub fore: Color,
+    pub back: Color,
+    pub ch: char,
+    pub flags: CharFlags,
+    pub filled: bool,
+    pub aspect_correction: bool,
+}
+impl Default for EllipseObject {
+    fn default() -> Self {
+        Self {
+            fore: Color::White,
+            back: Color::Black,
+            ch: '*',
+            flags: CharFlags::None,
+            filled: false,
+            aspect_correction: false,
+        }
+    }
+}
+impl EllipseObject {
+    fn draw(&self, surface: &mut Surface, rect: Rect) {
+        let mut w = rect.width() as f32;
+        let mut h = rect.height() as f32;
+        if self.aspect_correction {
+            // a terminal cell is about twice as tall as it is wide
+            let rows = h.min(w / 2.0);
+            h = rows;
+            w = rows * 2.0;
+        }
+        let cx = rect.left() as f32 + rect.width() as f32 / 2.0;
+        let cy = rect.top() as f32 + rect.height() as f32 / 2.0;
+        let rx = w / 2.0;
+        let ry = h / 2.0;
+        if rx <= 0.0 || ry <= 0.0 {
+            return;
+        }
+        let inside = |x: i32, y: i32| {
+            let nx = (x as f32 + 0.5 - cx) / rx;
+            let ny = (y as f32 + 0.5 - cy) / ry;
+            nx * nx + ny * ny <= 1.0
+        };
+        let ch = Character::new(self.ch, self.fore, self.back, self.flags);
+        for y in rect.top()..=rect.bottom() {
+            for x in rect.left()..=rect.right() {
+                if !inside(x, y) {
+                    continue;
+                }
+                let on_border = !inside(x - 1, y)
+                    || !inside(x + 1, y)
+                    || !inside(x, y - 1)
+                    || !inside(x, y + 1);
+                if self.filled || on_border {
+                    surface.write_char(x, y, ch);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum ArrowHead {
+    #[VariantInfo(name = "None", description = "No arrow head")]
+    None,
+    #[VariantInfo(name = "Triangle", description = "Filled triangle (► ▼)")]
+    Triangle,
+    #[VariantInfo(name = "Arrow", description = "Arrow (→ ↓)")]
+    Arrow,
+    #[VariantInfo(name = "Chevron", description = "ASCII chevron (> v)")]
+    Chevron,
+}
+impl ArrowHead {
+    fn glyph(&self, dir: usize) -> Option<char> {
+        let glyphs = match self {
+            ArrowHead::None => return None,
+            ArrowHead::Triangle => ['▲', '►', '▼', '◄'],
+            ArrowHead::Arrow => ['↑', '→', '↓', '←'],
+            ArrowHead::Chevron => ['^', '>', 'v', '<'],
+        };
+        Some(glyphs[dir])
+    }
+}
+
+pub struct ConnectorObject {
+    pub fore: Color,
+    pub back: Color,
+    pub flags: CharFlags,
+    pub line_type: LineType,
+    pub start_arrow: ArrowHead,
+    pub end_arrow: ArrowHead,
+    pub rounded: bool,
+    points: Vec<Point>,
+    cursor: Option<Point>,
+}
+impl Default for ConnectorObject {
+    fn default() -> Self {
+        Self {
+            fore: Color::White,
+            back: Color::Black,
+            flags: CharFlags::None,
+            line_type: LineType::Single,
+            start_arrow: ArrowHead::None,
+            end_arrow: ArrowHead::Triangle,
+            rounded: false,
+            points: Vec::new(),
+            cursor: None,
+        }
+    }
+}
+impl ConnectorObject {
+    // keeps the path orthogonal by inserting an elbow (horizontal first) when needed
+    fn push_orthogonal(points: &mut Vec<Point>, p: Point) {
+        if let Some(last) = points.last().copied() {
+            if last == p {
+                return;
+            }
+            if last.x != p.x && last.y != p.y {
+                points.push(Point::new(p.x, last.y));
+            }
+        }
+        points.push(p);
+    }
+    fn direction(from: Point, to: Point) -> usize {
+        if to.x > from.x {
+            box_join::RIGHT
+        } else if to.x < from.x {
+            box_join::LEFT
+        } else if to.y > from.y {
+            box_join::DOWN
+        } else {
+            box_join::UP
+        }
+    }
+    fn step(p: Point, dir: usize) -> Point {
+        match dir {
+            box_join::UP => Point::new(p.x, p.y - 1),
+            box_join::RIGHT => Point::new(p.x + 1, p.y),
+            box_join::DOWN => Point::new(p.x, p.y + 1),
+            _ => Point::new(p.x - 1, p.y),
+        }
+    }
+    fn glyph(&self, arms: [bool; 4]) -> char {
+        let horizontal = arms[box_join::LEFT] || arms[box_join::RIGHT];
+        let vertical = arms[box_join::UP] || arms[box_join::DOWN];
+        let weight = match self.line_type {
+            LineType::Double => box_join::D,
+            LineType::SingleThick => box_join::H,
+            LineType::Border => return '█',
+            LineType::Ascii | LineType::AsciiRound => {
+                return match (horizontal, vertical) {
+                    (true, false) => '-',
+                    (false, true) => '|',
+                    _ => '+',
+                };
+            }
+            LineType::Single | LineType::SingleRound | LineType::Braille => box_join::L,
+        };
+        let code =
+            box_join::char_with_arms(arms.map(|a| if a { weight } else { 0 })).unwrap_or('+');
+        if (self.rounded || self.line_type == LineType::SingleRound) && weight == box_join::L {
+            match code {
+                '┌' => '╭',
+                '┐' => '╮',
+                '┘' => '╯',
+                '└' => '╰',
+                _ => code,
+            }
+        } else {
+            code
+        }
+    }
+    fn draw(&self, surface: &mut Surface, with_cursor: bool) {
+        let mut pts = self.points.clone();
+        if let (true, Some(cursor)) = (with_cursor, self.cursor) {
+            Self::push_orthogonal(&mut pts, cursor);
+        }
+        if pts.len() < 2 {
+            return;
+        }
+        let mut ch = Character::new(' ', self.fore, self.back, self.flags);
+        // segments (without their ends)
+        for w in pts.windows(2) {
+            let dir = Self::direction(w[0], w[1]);
+            let mut arms = [false; 4];
+            arms[dir] = true;
+            arms[box_join::opposite(dir)] = true;
+            ch.code = self.glyph(arms);
+            let mut p = Self::step(w[0], dir);
+            while p != w[1] {
+                surface.write_char(p.x, p.y, ch);
+                p = Self::step(p, dir);
+            }
+        }
+        // waypoints (corners) and the two ends
+        let last = pts.len() - 1;
+        for i in 0..=last {
+            let mut arms = [false; 4];
+            if i > 0 {
+                arms[Self::direction(pts[i], pts[i - 1])] = true;
+            }
+            if i < last {
+                arms[Self::direction(pts[i], pts[i + 1])] = true;
+            }
+            let arrow = if i == 0 {
+                let dir = Self::direction(pts[1], pts[0]);
+                arms[dir] = true;
+                self.start_arrow.glyph(dir)
+            } else if i == last {
+                let dir = Self::direction(pts[last - 1], pts[last]);
+                arms[dir] = true;
+                self.end_arrow.glyph(dir)
+            } else {
+                None
+            };
+            ch.code = arrow.unwrap_or_else(|| self.glyph(arms));
+            surface.write_char(pts[i].x, pts[i].y, ch);
+        }
+    }
+}
+
+// Bresenham line between two cells (both ends included)
+pub(crate) fn line_points(from: Point, to: Point) -> Vec<Point> {
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let sx = if from.x < to.x { 1 } else { -1 };
+    let sy = if from.y < to.y { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut p = from;
+    let mut points = Vec::with_capacity((dx - dy + 1) as usize);
+    loop {
+        points.push(p);
+        if p == to {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            p.x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            p.y += sy;
+        }
+    }
+    points
+}
+
 pub enum DrawingObject {
     Selection(SelectionObject),
     Move(MoveObject),
@@ -91,6 +590,11 @@ pub enum DrawingObject {
     FillRectangle(FillRectangleObject),
     Line(LineObject),
     Text(TextObject),
+    Pencil(PencilObject),
+    Eraser(EraserObject),
+    FloodFill(FloodFillObject),
+    Ellipse(EllipseObject),
+    Connector(ConnectorObject),
 }
 
 impl DrawingObject {
@@ -98,7 +602,7 @@ impl DrawingObject {
         match self {
             DrawingObject::Selection(sel) => {
                 sel.img = None;
-                sel.start_point = Point::new(0, 0);
+                sel.source = Rect::new(0, 0, 0, 0);
             }
             DrawingObject::Rectangle(_) => {}
             DrawingObject::FillRectangle(_) => {}
@@ -107,39 +611,200 @@ impl DrawingObject {
                 text.txt.clear();
             }
             DrawingObject::Move(_) => {}
+            DrawingObject::Pencil(pencil) => {
+                pencil.last_point = None;
+                pencil.stroke_area = None;
+            }
+            DrawingObject::Eraser(eraser) => {
+                eraser.last_point = None;
+                eraser.stroke_area = None;
+            }
+            DrawingObject::FloodFill(_) => {}
+            DrawingObject::Ellipse(_) => {}
+            DrawingObject::Connector(connector) => {
+                connector.points.clear();
+                connector.cursor = None;
+            }
         }
     }
-    pub fn on_finish_selection(&mut self, surface: &Surface, rect: Rect) {
+    // name of the tool (used to label undo steps)
+    pub fn name(&self) -> &'static str {
         match self {
-            DrawingObject::Selection(sel) => {
-                let mut s = Surface::new(rect.width(), rect.height());
-                for y in 0..rect.height() as i32 {
-                    for x in 0..rect.width() as i32 {
-                        if let Some(ch) = surface.char(rect.left() + x, rect.top() + y) {
-                            s.write_char(x, y, *ch);
-                        }
-                    }
+            DrawingObject::Selection(_) => "Move selection",
+            DrawingObject::Move(_) => "Move",
+            DrawingObject::Rectangle(_) => "Rectangle",
+            DrawingObject::FillRectangle(_) => "Filled rectangle",
+            DrawingObject::Line(_) => "Line",
+            DrawingObject::Text(_) => "Text",
+            DrawingObject::Pencil(_) => "Pencil",
+            DrawingObject::Eraser(_) => "Eraser",
+            DrawingObject::FloodFill(_) => "Fill",
+            DrawingObject::Ellipse(_) => "Ellipse",
+            DrawingObject::Connector(_) => "Connector",
+        }
+    }
+    // the part of a canvas of the given size that `paint` may change
+    pub fn touched_area(&self, rect: Rect, canvas: Size) -> Rect {
+        match self {
+            // the area the image was taken from is cleared as well
+            DrawingObject::Selection(SelectionObject {
+                img: Some(_),
+                source,
+                ..
+            }) => layer::union(rect, *source),
+            // word-wrapped text can go below the rectangle
+            DrawingObject::Text(_) => Rect::new(
+                rect.left(),
+                rect.top(),
+                rect.right(),
+                rect.bottom().max(canvas.height as i32 - 1),
+            ),
+            _ => rect,
+        }
+    }
+    pub fn auto_join(&self) -> bool {
+        match self {
+            DrawingObject::Rectangle(rectangle) => rectangle.auto_join,
+            DrawingObject::Line(line) => line.auto_join,
+            _ => false,
+        }
+    }
+    pub fn is_path(&self) -> bool {
+        matches!(self, DrawingObject::Connector(_))
+    }
+    pub fn has_path(&self) -> bool {
+        if let DrawingObject::Connector(connector) = self {
+            !connector.points.is_empty()
+        } else {
+            false
+        }
+    }
+    pub fn add_path_point(&mut self, point: Point) {
+        if let DrawingObject::Connector(connector) = self {
+            ConnectorObject::push_orthogonal(&mut connector.points, point);
+            connector.cursor = Some(point);
+        }
+    }
+    pub fn set_path_cursor(&mut self, point: Point) {
+        if let DrawingObject::Connector(connector) = self {
+            connector.cursor = Some(point);
+        }
+    }
+    // the part of the canvas that `paint_path` changes (without the preview)
+    pub fn path_area(&self) -> Option<Rect> {
+        let DrawingObject::Connector(connector) = self else {
+            return None;
+        };
+        let first = *connector.points.first()?;
+        Some(
+            connector
+                .points
+                .iter()
+                .fold(Rect::new(first.x, first.y, first.x, first.y), |area, p| {
+                    layer::union(area, Rect::new(p.x, p.y, p.x, p.y))
+                }),
+        )
+    }
+    pub fn paint_path(&self, surface: &mut Surface, preview: bool) {
+        if let DrawingObject::Connector(connector) = self {
+            connector.draw(surface, preview);
+        }
+    }
+    pub fn is_stroke(&self) -> bool {
+        matches!(self, DrawingObject::Pencil(_) | DrawingObject::Eraser(_))
+    }
+    pub fn is_click(&self) -> bool {
+        matches!(self, DrawingObject::FloodFill(_))
+    }
+    // returns the part of the canvas that was changed (`None` if nothing was)
+    pub fn click(&mut self, cells: &mut Cells, point: Point) -> Option<Rect> {
+        if let DrawingObject::FloodFill(fill) = self {
+            fill.fill(cells, point)
+        } else {
+            None
+        }
+    }
+    pub fn stroke_to(&mut self, cells: &mut Cells, point: Point) {
+        // fill the gap between two drag samples so that fast strokes stay continuous
+        match self {
+            DrawingObject::Pencil(pencil) => {
+                let ch = Character::new(pencil.ch, pencil.fore, pencil.back, pencil.flags);
+                let from = pencil.last_point.unwrap_or(point);
+                for p in line_points(from, point) {
+                    cells.write_char(p.x, p.y, ch);
+                }
+                let segment = Rect::new(from.x, from.y, point.x, point.y);
+                pencil.stroke_area = Some(
+                    pencil
+                        .stroke_area
+                        .map_or(segment, |a| layer::union(a, segment)),
+                );
+                pencil.last_point = Some(point);
+            }
+            DrawingObject::Eraser(eraser) => {
+                let from = eraser.last_point.unwrap_or(point);
+                // the blank is written as it is (a transparent blank makes the cells transparent)
+                for p in line_points(from, point) {
+                    cells.set_rect(eraser.brush_rect(p), eraser.blank);
                 }
-                sel.img = Some(s);
-                sel.start_point = Point::new(rect.left(), rect.top());
+                let segment = layer::union(eraser.brush_rect(from), eraser.brush_rect(point));
+                eraser.stroke_area = Some(
+                    eraser
+                        .stroke_area
+                        .map_or(segment, |a| layer::union(a, segment)),
+                );
+                eraser.last_point = Some(point);
+            }
+            _ => {}
+        }
+    }
+    // flips / rotates the floating selection (returns false if there is none)
+    pub fn transform_selection(&mut self, transform: Transform) -> bool {
+        if let DrawingObject::Selection(SelectionObject { img: Some(img), .. }) = self {
+            *img = transform.apply(img);
+            true
+        } else {
+            false
+        }
+    }
+    // returns the part of the canvas the stroke went over (`None` if there was no stroke)
+    pub fn end_stroke(&mut self) -> Option<Rect> {
+        match self {
+            DrawingObject::Pencil(pencil) => {
+                pencil.last_point = None;
+                pencil.stroke_area.take()
+            }
+            DrawingObject::Eraser(eraser) => {
+                eraser.last_point = None;
+                eraser.stroke_area.take()
+            }
+            _ => None,
+        }
+    }
+    pub fn on_finish_selection(&mut self, cells: &Cells, rect: Rect) {
+        match self {
+            DrawingObject::Selection(sel) => {
+                sel.img = Some(layer::crop_surface(cells, rect, layer::EMPTY));
+                sel.source = rect;
             }
             DrawingObject::Rectangle(_)
             | DrawingObject::FillRectangle(_)
             | DrawingObject::Line(_)
             | DrawingObject::Text(_)
-            | DrawingObject::Move(_) => {}
+            | DrawingObject::Move(_)
+            | DrawingObject::Pencil(_)
+            | DrawingObject::Eraser(_)
+            | DrawingObject::FloodFill(_)
+            | DrawingObject::Ellipse(_)
+            | DrawingObject::Connector(_) => {}
         }
     }
-    pub fn paint(&self, surface: &mut Surface, rect: Rect) {
+    pub fn paint(&self, surface: &mut Surface, rect: Rect, origin: Point) {
         match self {
             DrawingObject::Selection(sel) => {
                 if let Some(img) = &sel.img {
-                    let r = Rect::with_point_and_size(
-                        sel.start_point,
-                        Size::new(rect.width(), rect.height()),
-                    );
                     surface.fill_rect(
-                        r,
+                        sel.source,
                         Character::new(
                             ' ',
                             Color::Transparent,
@@ -147,14 +812,21 @@ impl DrawingObject {
                             CharFlags::None,
                         ),
                     );
-                    surface.draw_surface(rect.left(), rect.top(), img);
+                    // the image is stretched to the (resized) selection
+                    let size = Size::new(rect.width(), rect.height());
+                    if img.size() == size {
+                        surface.draw_surface(rect.left(), rect.top(), img);
+                    } else {
+                        let scaled = transform::scale(img, size, sel.nine_slice);
+                        surface.draw_surface(rect.left(), rect.top(), &scaled);
+                    }
                 }
             }
             DrawingObject::Rectangle(rectangle) => {
                 surface.draw_rect(
                     rect,
                     rectangle.line_type,
-                    CharAttribute::with_color(rectangle.fore, rectangle.back),
+                    CharAttribute::new(rectangle.fore, rectangle.back, rectangle.flags),
                 );
             }
             DrawingObject::FillRectangle(fill_rect) => {
@@ -169,23 +841,25 @@ impl DrawingObject {
                 );
             }
             DrawingObject::Line(line) => {
-                if line.vertical {
-                    surface.draw_vertical_line(
-                        rect.center_x(),
-                        rect.top(),
-                        rect.bottom(),
-                        line.line_type,
-                        CharAttribute::with_color(line.fore, line.back),
-                    );
-                } else {
-                    surface.draw_horizontal_line(
-                        rect.left(),
-                        rect.center_y(),
-                        rect.right(),
-                        line.line_type,
-                        CharAttribute::with_color(line.fore, line.back),
-                    );
-                }
+                let (from, to) = match line.direction {
+                    LineDirection::Vertical => (
+                        Point::new(rect.center_x(), rect.top()),
+                        Point::new(rect.center_x(), rect.bottom()),
+                    ),
+                    LineDirection::Horizontal => (
+                        Point::new(rect.left(), rect.center_y()),
+                        Point::new(rect.right(), rect.center_y()),
+                    ),
+                    // from the corner where the selection started to the opposite one
+                    LineDirection::Free => (
+                        origin,
+                        Point::new(
+                            rect.left() + rect.right() - origin.x,
+                            rect.top() + rect.bottom() - origin.y,
+                        ),
+                    ),
+                };
+                line.draw(surface, from, to);
             }
             DrawingObject::Text(text) => {
                 let tf = TextFormatBuilder::new()
@@ -196,6 +870,91 @@ impl DrawingObject {
                 surface.write_text(&text.txt, &tf);
             }
             DrawingObject::Move(_) => {}
+            DrawingObject::Pencil(_) => {}
+            DrawingObject::Eraser(_) => {}
+            DrawingObject::FloodFill(_) => {}
+            DrawingObject::Ellipse(ellipse) => ellipse.draw(surface, rect),
+            DrawingObject::Connector(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history;
+    use crate::history::History;
+    use crate::layer::Layer;
+
+    fn filled_layer(code: char) -> Layer {
+        let fill = Character::new(code, Color::White, Color::Black, CharFlags::None);
+        Layer::with_cells("Background", Cells::new(6, 4, fill))
+    }
+
+    fn code(layer: &Layer, x: i32, y: i32) -> char {
+        layer.cells.char(x, y).unwrap().code
+    }
+
+    #[test]
+    fn eraser_restores_transparent_cells() {
+        let mut layer = Layer::new("Layer", 6, 4);
+        layer.cells.write_char(
+            2,
+            1,
+            Character::new('X', Color::Red, Color::Transparent, CharFlags::None),
+        );
+        let mut eraser = DrawingObject::Eraser(EraserObject {
+            blank: layer::EMPTY,
+            ..EraserObject::default()
+        });
+        eraser.stroke_to(&mut layer.cells, Point::new(2, 1));
+        assert_eq!(eraser.end_stroke(), Some(Rect::new(2, 1, 2, 1)));
+        assert_eq!(layer.cells.char(2, 1), Some(&layer::EMPTY));
+        assert_eq!(layer.cells, Layer::new("Layer", 6, 4).cells);
+    }
+
+    #[test]
+    fn moved_selection_is_undone() {
+        let mut layers = vec![filled_layer('.')];
+        for (x, code) in [(0, 'A'), (1, 'B')] {
+            layers[0].cells.write_char(
+                x,
+                0,
+                Character::new(code, Color::Red, Color::Black, CharFlags::None),
+            );
+        }
+        let mut history = History::new(&layers, 0, history::DEFAULT_BUDGET);
+        let original = layers[0].cells.clone();
+
+        let mut object = DrawingObject::Selection(SelectionObject::default());
+        object.on_finish_selection(&layers[0].cells, Rect::new(0, 0, 1, 0));
+        let rect = Rect::new(3, 2, 4, 2);
+        let area = object.touched_area(rect, layers[0].cells.size());
+        if let DrawingObject::Selection(sel) = &object {
+            sel.place(&mut layers[0].cells, rect, true);
+        }
+        assert!(history.record_cells("Move selection", &layers, 0, Some(area)));
+        assert_eq!(code(&layers[0], 3, 2), 'A');
+        assert_eq!(code(&layers[0], 0, 0), ' ');
+
+        history.undo(&mut layers);
+        assert_eq!(layers[0].cells, original);
+    }
+
+    #[test]
+    fn moved_selection_keeps_transparent_cells() {
+        let mut layer = Layer::new("Layer", 6, 4);
+        let x = Character::new('X', Color::Red, Color::Transparent, CharFlags::None);
+        layer.cells.write_char(0, 0, x);
+        layer.cells.write_char(4, 2, x);
+        let mut object = DrawingObject::Selection(SelectionObject::default());
+        object.on_finish_selection(&layer.cells, Rect::new(0, 0, 1, 0));
+        if let DrawingObject::Selection(sel) = &object {
+            sel.place(&mut layer.cells, Rect::new(3, 2, 4, 2), false);
         }
+        assert_eq!(layer.cells.char(0, 0), Some(&layer::EMPTY));
+        assert_eq!(layer.cells.char(3, 2), Some(&x));
+        // the transparent cell of the image does not cover the painting below it
+        assert_eq!(layer.cells.char(4, 2), Some(&x));
     }
 }
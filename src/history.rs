@@ -0,0 +1,531 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use appcui::prelude::*;
+
+use crate::layer;
+use crate::layer::Cells;
+use crate::layer::Layer;
+
+// memory that the undo / redo steps of a painting may use
+pub const DEFAULT_BUDGET: usize = 32 * 1024 * 1024;
+
+#[derive(Clone, PartialEq)]
+pub struct LayerProperties {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: u8,
+    pub locked: bool,
+}
+
+impl LayerProperties {
+    pub fn of(layer: &Layer) -> Self {
+        Self {
+            name: layer.name.clone(),
+            visible: layer.visible,
+            opacity: layer.opacity,
+            locked: layer.locked,
+        }
+    }
+
+    pub fn apply(&self, layer: &mut Layer) {
+        layer.name = self.name.clone();
+        layer.visible = self.visible;
+        layer.opacity = self.opacity;
+        layer.locked = self.locked;
+    }
+}
+
+pub enum Change {
+    // the cells of `rect` (row by row) before and after the edit
+    Cells {
+        layer: usize,
+        rect: Rect,
+        before: Vec<Character>,
+        after: Vec<Character>,
+    },
+    Properties {
+        layer: usize,
+        before: LayerProperties,
+        after: LayerProperties,
+    },
+    Insert {
+        index: usize,
+        layer: Layer,
+    },
+    Remove {
+        index: usize,
+        layer: Layer,
+    },
+    // the layers `index` and `index + 1` changed places
+    Swap {
+        index: usize,
+    },
+    // every layer was cropped to `rect` (see `layer::crop_layers`)
+    Canvas {
+        before: Vec<Layer>,
+        rect: Rect,
+        fill: Character,
+    },
+}
+
+impl Change {
+    fn memory(&self) -> usize {
+        let cells = |l: &Layer| {
+            let size = l.cells.size();
+            (size.width * size.height) as usize
+        };
+        size_of::<Self>()
+            + size_of::<Character>()
+                * match self {
+                    Change::Cells { before, after, .. } => before.len() + after.len(),
+                    Change::Insert { layer, .. } | Change::Remove { layer, .. } => cells(layer),
+                    Change::Canvas { before, .. } => before.iter().map(cells).sum(),
+                    Change::Properties { .. } | Change::Swap { .. } => 0,
+                }
+    }
+
+    fn undo(&self, layers: &mut Vec<Layer>) {
+        match self {
+            Change::Cells {
+                layer,
+                rect,
+                before,
+                ..
+            } => layers[*layer].cells.patch(*rect, before),
+            Change::Properties { layer, before, .. } => before.apply(&mut layers[*layer]),
+            Change::Insert { index, .. } => {
+                layers.remove(*index);
+            }
+            Change::Remove { index, layer } => layers.insert(*index, layer.clone()),
+            Change::Swap { index } => layers.swap(*index, *index + 1),
+            Change::Canvas { before, .. } => *layers = before.clone(),
+        }
+    }
+
+    fn redo(&self, layers: &mut Vec<Layer>) {
+        match self {
+            Change::Cells {
+                layer, rect, after, ..
+            } => layers[*layer].cells.patch(*rect, after),
+            Change::Properties { layer, after, .. } => after.apply(&mut layers[*layer]),
+            Change::Insert { index, layer } => layers.insert(*index, layer.clone()),
+            Change::Remove { index, .. } => {
+                layers.remove(*index);
+            }
+            Change::Swap { index } => layers.swap(*index, *index + 1),
+            Change::Canvas { rect, fill, .. } => layer::crop_layers(layers, *rect, *fill),
+        }
+    }
+}
+
+// the changed cells between two layers of the same size (`None` if they are identical); only the `area`
+// part of the layers is compared
+pub fn diff(layer: usize, before: &Cells, after: &Cells, area: Rect) -> Option<Change> {
+    let size = after.size();
+    let mut bounds: Option<(i32, i32, i32, i32)> = None;
+    for y in area.top().max(0)..=area.bottom().min(size.height as i32 - 1) {
+        for x in area.left().max(0)..=area.right().min(size.width as i32 - 1) {
+            if before.char(x, y) != after.char(x, y) {
+                bounds = Some(match bounds {
+                    Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x), b.max(y)),
+                    None => (x, y, x, y),
+                });
+            }
+        }
+    }
+    let (l, t, r, b) = bounds?;
+    let rect = Rect::new(l, t, r, b);
+    Some(Change::Cells {
+        layer,
+        rect,
+        before: before.region(rect),
+        after: after.region(rect),
+    })
+}
+
+struct Entry {
+    // the operation that created the step ("Rectangle 10x4", "Paste", ...)
+    name: String,
+    changes: Vec<Change>,
+    // active layer before and after the step
+    active: (usize, usize),
+    memory: usize,
+}
+
+impl Entry {
+    // only cell edits of the given layer (the shadow copy can be patched instead of copied)
+    fn only_cells_of(&self, index: usize) -> bool {
+        self.active == (index, index)
+            && self.changes.iter().all(|c| match c {
+                Change::Cells { layer, .. } => *layer == index,
+                Change::Properties { .. } => true,
+                _ => false,
+            })
+    }
+}
+
+pub struct History {
+    // oldest steps are dropped from the front once the budget is exceeded
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    memory: usize,
+    budget: usize,
+    // the active layer as it was after the last recorded step: edits are found by comparing with it
+    shadow: Cells,
+    shadow_layer: usize,
+}
+
+impl History {
+    pub fn new(layers: &[Layer], active: usize, budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            memory: 0,
+            budget,
+            shadow: layers[active].cells.clone(),
+            shadow_layer: active,
+        }
+    }
+
+    // must be called when the active layer changes without an undo step
+    pub fn sync(&mut self, layers: &[Layer], active: usize) {
+        self.shadow = layers[active].cells.clone();
+        self.shadow_layer = active;
+    }
+
+    pub fn clear(&mut self, layers: &[Layer], active: usize) {
+        self.undo.clear();
+        self.redo.clear();
+        self.memory = 0;
+        self.sync(layers, active);
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.memory -= self.redo.iter().map(|e| e.memory).sum::<usize>();
+        self.redo.clear();
+        self.memory += entry.memory;
+        self.undo.push_back(entry);
+        // the newest step is always kept
+        while self.memory > self.budget && self.undo.len() > 1 {
+            if let Some(old) = self.undo.pop_front() {
+                self.memory -= old.memory;
+            }
+        }
+    }
+
+    // records the cells of the active layer changed since the last step (returns false if nothing changed);
+    // `area` (if known) is the part of the layer that could have been changed
+    pub fn record_cells(
+        &mut self,
+        name: &str,
+        layers: &[Layer],
+        active: usize,
+        area: Option<Rect>,
+    ) -> bool {
+        let cells = &layers[active].cells;
+        let area = area.unwrap_or(Rect::with_point_and_size(Point::new(0, 0), cells.size()));
+        let Some(change) = diff(active, &self.shadow, cells, area) else {
+            return false;
+        };
+        if let Change::Cells { rect, after, .. } = &change {
+            self.shadow.patch(*rect, after);
+        }
+        self.push(Entry {
+            name: name.to_string(),
+            memory: change.memory(),
+            changes: vec![change],
+            active: (active, active),
+        });
+        true
+    }
+
+    // records changes that were already applied to `layers`
+    pub fn record(
+        &mut self,
+        name: &str,
+        changes: Vec<Change>,
+        active: (usize, usize),
+        layers: &[Layer],
+    ) {
+        let entry = Entry {
+            name: name.to_string(),
+            memory: changes.iter().map(Change::memory).sum(),
+            changes,
+            active,
+        };
+        if !entry.only_cells_of(self.shadow_layer) {
+            self.sync(layers, active.1);
+        } else {
+            for change in entry.changes.iter() {
+                if let Change::Cells { rect, after, .. } = change {
+                    self.shadow.patch(*rect, after);
+                }
+            }
+        }
+        self.push(entry);
+    }
+
+    // reverts the last step and returns the active layer
+    pub fn undo(&mut self, layers: &mut Vec<Layer>) -> Option<usize> {
+        let entry = self.undo.pop_back()?;
+        let patch_shadow = entry.only_cells_of(self.shadow_layer);
+        for change in entry.changes.iter().rev() {
+            change.undo(layers);
+            if let (true, Change::Cells { rect, before, .. }) = (patch_shadow, change) {
+                self.shadow.patch(*rect, before);
+            }
+        }
+        let active = entry.active.0;
+        if !patch_shadow {
+            self.sync(layers, active);
+        }
+        self.redo.push(entry);
+        Some(active)
+    }
+
+    // applies the last reverted step again and returns the active layer
+    pub fn redo(&mut self, layers: &mut Vec<Layer>) -> Option<usize> {
+        let entry = self.redo.pop()?;
+        let patch_shadow = entry.only_cells_of(self.shadow_layer);
+        for change in entry.changes.iter() {
+            change.redo(layers);
+            if let (true, Change::Cells { rect, after, .. }) = (patch_shadow, change) {
+                self.shadow.patch(*rect, after);
+            }
+        }
+        let active = entry.active.1;
+        if !patch_shadow {
+            self.sync(layers, active);
+        }
+        self.undo.push_back(entry);
+        Some(active)
+    }
+
+    // undoes / redoes steps until `position` steps are applied and returns the active layer (`None` if
+    // nothing changed)
+    pub fn jump(&mut self, layers: &mut Vec<Layer>, position: usize) -> Option<usize> {
+        let mut active = None;
+        while self.undo.len() > position {
+            active = self.undo(layers);
+        }
+        while self.undo.len() < position {
+            match self.redo(layers) {
+                Some(index) => active = Some(index),
+                None => break,
+            }
+        }
+        active
+    }
+
+    // the names of all the steps, oldest first (the undone ones come after `position`)
+    pub fn steps(&self) -> impl Iterator<Item = &str> {
+        self.undo
+            .iter()
+            .chain(self.redo.iter().rev())
+            .map(|e| e.name.as_str())
+    }
+
+    // number of steps currently applied
+    pub fn position(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // bytes used by the recorded steps
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled_layer(name: &str, code: char) -> Layer {
+        let fill = Character::new(code, Color::White, Color::Black, CharFlags::None);
+        Layer::with_cells(name, Cells::new(6, 4, fill))
+    }
+
+    fn code(layers: &[Layer], index: usize, x: i32, y: i32) -> char {
+        layers[index].cells.char(x, y).unwrap().code
+    }
+
+    fn draw(layers: &mut [Layer], x: i32, y: i32, code: char) {
+        layers[0].cells.write_char(
+            x,
+            y,
+            Character::new(code, Color::Red, Color::Black, CharFlags::None),
+        );
+    }
+
+    #[test]
+    fn record_cells_undo_redo() {
+        let mut layers = vec![filled_layer("Background", '.')];
+        let mut history = History::new(&layers, 0, DEFAULT_BUDGET);
+        assert!(!history.record_cells("Pencil", &layers, 0, None));
+
+        draw(&mut layers, 2, 1, 'X');
+        assert!(history.record_cells("Pencil", &layers, 0, None));
+        assert!(!history.record_cells("Pencil", &layers, 0, None));
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+
+        assert_eq!(history.undo(&mut layers), Some(0));
+        assert_eq!(code(&layers, 0, 2, 1), '.');
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+        assert_eq!(history.undo(&mut layers), None);
+
+        assert_eq!(history.redo(&mut layers), Some(0));
+        assert_eq!(code(&layers, 0, 2, 1), 'X');
+        assert_eq!(history.redo(&mut layers), None);
+    }
+
+    #[test]
+    fn record_cells_outside_of_area_are_ignored() {
+        let mut layers = vec![filled_layer("Background", '.')];
+        let mut history = History::new(&layers, 0, DEFAULT_BUDGET);
+        draw(&mut layers, 4, 3, 'X');
+        assert!(!history.record_cells("Pencil", &layers, 0, Some(Rect::new(0, 0, 1, 1))));
+    }
+
+    #[test]
+    fn jump() {
+        let mut layers = vec![filled_layer("Background", '.')];
+        let mut history = History::new(&layers, 0, DEFAULT_BUDGET);
+        for (x, name) in [(0, "A"), (1, "B"), (2, "C")] {
+            draw(&mut layers, x, 0, 'X');
+            history.record_cells(name, &layers, 0, None);
+        }
+        assert_eq!(history.jump(&mut layers, 3), None);
+
+        assert_eq!(history.jump(&mut layers, 1), Some(0));
+        assert_eq!(history.position(), 1);
+        assert_eq!(history.steps().collect::<Vec<_>>(), ["A", "B", "C"]);
+        assert_eq!(code(&layers, 0, 0, 0), 'X');
+        assert_eq!(code(&layers, 0, 1, 0), '.');
+        assert_eq!(code(&layers, 0, 2, 0), '.');
+
+        assert_eq!(history.jump(&mut layers, 0), Some(0));
+        assert!(!history.can_undo());
+        assert_eq!(code(&layers, 0, 0, 0), '.');
+
+        assert_eq!(history.jump(&mut layers, 10), Some(0));
+        assert_eq!(history.position(), 3);
+        assert_eq!(code(&layers, 0, 2, 0), 'X');
+
+        // a new step drops the undone ones
+        history.jump(&mut layers, 1);
+        draw(&mut layers, 5, 3, 'Y');
+        history.record_cells("D", &layers, 0, None);
+        assert_eq!(history.steps().collect::<Vec<_>>(), ["A", "D"]);
+    }
+
+    #[test]
+    fn budget_drops_oldest_steps() {
+        // every step changes a single cell
+        let step = size_of::<Change>() + 2 * size_of::<Character>();
+        let mut layers = vec![filled_layer("Background", '.')];
+        let mut history = History::new(&layers, 0, 2 * step);
+        for (x, name) in [(0, "A"), (1, "B"), (2, "C")] {
+            draw(&mut layers, x, 0, 'X');
+            history.record_cells(name, &layers, 0, None);
+        }
+        assert_eq!(history.steps().collect::<Vec<_>>(), ["B", "C"]);
+        assert_eq!(history.memory(), 2 * step);
+
+        // the newest step is kept even if it does not fit
+        let mut history = History::new(&layers, 0, 0);
+        draw(&mut layers, 3, 0, 'X');
+        history.record_cells("D", &layers, 0, None);
+        assert_eq!(history.steps().collect::<Vec<_>>(), ["D"]);
+        assert_eq!(history.memory(), step);
+    }
+
+    #[test]
+    fn merge_down_round_trip() {
+        let mut top = Layer::new("Top", 6, 4);
+        top.cells.write_char(
+            1,
+            1,
+            Character::new('X', Color::Red, Color::Transparent, CharFlags::None),
+        );
+        let mut layers = vec![filled_layer("Background", '.'), top];
+        let mut history = History::new(&layers, 1, DEFAULT_BUDGET);
+        let original: Vec<_> = layers.iter().map(layer::cells).collect();
+
+        let upper = layers.remove(1);
+        let merged = layer::merge(&layers[0], &upper);
+        let area = Rect::new(0, 0, 5, 3);
+        let mut changes = vec![Change::Remove {
+            index: 1,
+            layer: upper,
+        }];
+        changes.extend(diff(0, &layers[0].cells, &merged, area));
+        layers[0].cells = merged;
+        history.record("Merge down", changes, (1, 0), &layers);
+        let merged: Vec<_> = layers.iter().map(layer::cells).collect();
+
+        assert_eq!(history.undo(&mut layers), Some(1));
+        assert_eq!(
+            layers.iter().map(layer::cells).collect::<Vec<_>>(),
+            original
+        );
+        assert_eq!(layers[1].name, "Top");
+
+        assert_eq!(history.redo(&mut layers), Some(0));
+        assert_eq!(layers.iter().map(layer::cells).collect::<Vec<_>>(), merged);
+        assert_eq!(code(&layers, 0, 1, 1), 'X');
+    }
+
+    #[test]
+    fn delete_round_trip() {
+        let mut layers = vec![filled_layer("Background", '.'), filled_layer("Top", '#')];
+        let mut history = History::new(&layers, 1, DEFAULT_BUDGET);
+        let layer = layers.remove(1);
+        history.record(
+            "Delete layer",
+            vec![Change::Remove { index: 1, layer }],
+            (1, 0),
+            &layers,
+        );
+
+        assert_eq!(history.undo(&mut layers), Some(1));
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[1].name, "Top");
+        assert_eq!(code(&layers, 1, 0, 0), '#');
+
+        assert_eq!(history.redo(&mut layers), Some(0));
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].name, "Background");
+    }
+
+    #[test]
+    fn swap_round_trip() {
+        let mut layers = vec![filled_layer("Background", '.'), filled_layer("Top", '#')];
+        let mut history = History::new(&layers, 0, DEFAULT_BUDGET);
+        layers.swap(0, 1);
+        history.record(
+            "Move layer up",
+            vec![Change::Swap { index: 0 }],
+            (0, 1),
+            &layers,
+        );
+
+        assert_eq!(history.undo(&mut layers), Some(0));
+        assert_eq!(layers[0].name, "Background");
+        assert_eq!(layers[1].name, "Top");
+
+        assert_eq!(history.redo(&mut layers), Some(1));
+        assert_eq!(layers[0].name, "Top");
+        assert_eq!(code(&layers, 0, 0, 0), '#');
+    }
+}
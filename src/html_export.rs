@@ -0,0 +1,217 @@
+use std::fmt::Write;
+
+use appcui::prelude::*;
+
+use crate::colors;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum HtmlStyling {
+    #[VariantInfo(
+        name = "Inline styles",
+        description = "Every span carries its own style attribute"
+    )]
+    Inline,
+    #[VariantInfo(
+        name = "CSS classes",
+        description = "A palette of CSS classes is embedded in the page"
+    )]
+    Classes,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum PageTheme {
+    #[VariantInfo(name = "Dark", description = "Black page background")]
+    Dark,
+    #[VariantInfo(name = "Light", description = "White page background")]
+    Light,
+}
+
+#[derive(Copy, Clone)]
+pub struct HtmlExportOptions {
+    pub styling: HtmlStyling,
+    pub theme: PageTheme,
+}
+
+fn hex(rgb: (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+fn escape(output: &mut String, ch: char) {
+    match ch {
+        '&' => output.push_str("&amp;"),
+        '<' => output.push_str("&lt;"),
+        '>' => output.push_str("&gt;"),
+        '"' => output.push_str("&quot;"),
+        c if c == 0 as char => output.push(' '),
+        c => output.push(c),
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct Attributes {
+    fore: Color,
+    back: Color,
+    flags: CharFlags,
+}
+
+impl Attributes {
+    fn is_default(&self) -> bool {
+        self.fore == Color::Transparent
+            && self.back == Color::Transparent
+            && self.flags == CharFlags::None
+    }
+
+    // opening tag of the span for these attributes (named colors use the palette classes when available)
+    fn open_tag(&self, styling: HtmlStyling) -> String {
+        let mut classes = Vec::new();
+        let mut styles = Vec::new();
+        for (color, background) in [(self.fore, false), (self.back, true)] {
+            let prefix = if background { "b" } else { "f" };
+            let property = if background {
+                "background-color"
+            } else {
+                "color"
+            };
+            match (colors::index(color), colors::rgb(color)) {
+                (Some(idx), _) if styling == HtmlStyling::Classes => {
+                    classes.push(format!("{}{}", prefix, idx))
+                }
+                (_, Some(rgb)) => styles.push(format!("{}:{}", property, hex(rgb))),
+                _ => {}
+            }
+        }
+        for (flag, class, style) in [
+            (CharFlags::Bold, "bold", "font-weight:bold"),
+            (CharFlags::Italic, "italic", "font-style:italic"),
+            (
+                CharFlags::Underline,
+                "underline",
+                "text-decoration:underline",
+            ),
+        ] {
+            if self.flags.contains(flag) {
+                if styling == HtmlStyling::Classes {
+                    classes.push(class.to_string());
+                } else {
+                    styles.push(style.to_string());
+                }
+            }
+        }
+        let mut tag = String::from("<span");
+        if !classes.is_empty() {
+            let _ = write!(tag, " class=\"{}\"", classes.join(" "));
+        }
+        if !styles.is_empty() {
+            let _ = write!(tag, " style=\"{}\"", styles.join(";"));
+        }
+        tag.push('>');
+        tag
+    }
+}
+
+fn stylesheet(options: &HtmlExportOptions) -> String {
+    let (page_back, page_fore) = match options.theme {
+        PageTheme::Dark => ("#000000", "#c0c0c0"),
+        PageTheme::Light => ("#ffffff", "#000000"),
+    };
+    let mut css = String::new();
+    let _ = writeln!(
+        css,
+        "body {{ background-color: {}; color: {}; }}",
+        page_back, page_fore
+    );
+    let _ = writeln!(css, "pre {{ font-family: monospace; line-height: 1.2; }}");
+    if options.styling == HtmlStyling::Classes {
+        for (idx, rgb) in colors::PALETTE.iter().enumerate() {
+            let _ = writeln!(css, ".f{} {{ color: {}; }}", idx, hex(*rgb));
+            let _ = writeln!(css, ".b{} {{ background-color: {}; }}", idx, hex(*rgb));
+        }
+        let _ = writeln!(css, ".bold {{ font-weight: bold; }}");
+        let _ = writeln!(css, ".italic {{ font-style: italic; }}");
+        let _ = writeln!(css, ".underline {{ text-decoration: underline; }}");
+    }
+    css
+}
+
+pub fn export(surface: &Surface, options: &HtmlExportOptions) -> String {
+    let size = surface.size();
+    let mut output = String::new();
+    output.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    output.push_str("<title>AsciiPainter</title>\n<style>\n");
+    output.push_str(&stylesheet(options));
+    output.push_str("</style>\n</head>\n<body>\n<pre>");
+    for y in 0..size.height as i32 {
+        // consecutive cells with the same attributes are merged into a single span
+        let mut current: Option<Attributes> = None;
+        for x in 0..size.width as i32 {
+            let Some(ch) = surface.char(x, y) else {
+                continue;
+            };
+            let attr = Attributes {
+                fore: ch.foreground,
+                back: ch.background,
+                flags: ch.flags,
+            };
+            if current != Some(attr) {
+                if current.is_some_and(|c| !c.is_default()) {
+                    output.push_str("</span>");
+                }
+                if !attr.is_default() {
+                    output.push_str(&attr.open_tag(options.styling));
+                }
+                current = Some(attr);
+            }
+            escape(&mut output, ch.code);
+        }
+        if current.is_some_and(|c| !c.is_default()) {
+            output.push_str("</span>");
+        }
+        output.push('\n');
+    }
+    output.push_str("</pre>\n</body>\n</html>\n");
+    output
+}
+
+#[ModalWindow(events = ButtonEvents, response = HtmlExportOptions)]
+pub struct HtmlExportDialog {
+    styling: Handle<Selector<HtmlStyling>>,
+    theme: Handle<Selector<PageTheme>>,
+    export: Handle<Button>,
+}
+
+impl HtmlExportDialog {
+    pub fn new() -> Self {
+        let mut w = Self {
+            base: ModalWindow::new(
+                "Export as HTML",
+                layout!("a:c,w:44,h:9"),
+                window::Flags::None,
+            ),
+            styling: Handle::None,
+            theme: Handle::None,
+            export: Handle::None,
+        };
+        w.add(label!("'Styling:',x:1,y:1,w:14,h:1"));
+        w.styling = w.add(selector!("HtmlStyling,x:16,y:1,w:25,value:Inline"));
+        w.add(label!("'Page:',x:1,y:3,w:14,h:1"));
+        w.theme = w.add(selector!("PageTheme,x:16,y:3,w:25,value:Dark"));
+        w.export = w.add(button!("&Export,x:8,y:5,w:12"));
+        w.add(button!("&Cancel,x:22,y:5,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for HtmlExportDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.export {
+            let options = HtmlExportOptions {
+                styling: self.control(self.styling).unwrap().value(),
+                theme: self.control(self.theme).unwrap().value(),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
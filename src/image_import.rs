@@ -0,0 +1,377 @@
+use appcui::prelude::*;
+
+use crate::colors;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum ImageStrategy {
+    #[VariantInfo(
+        name = "Character ramp",
+        description = "One pixel per cell, brightness mapped to ASCII characters"
+    )]
+    Ramp,
+    #[VariantInfo(
+        name = "Half blocks",
+        description = "Two vertical pixels per cell (▀ with foreground and background colors)"
+    )]
+    HalfBlock,
+    #[VariantInfo(
+        name = "Quadrants",
+        description = "Four pixels per cell drawn with quadrant block characters"
+    )]
+    Quadrant,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum Dithering {
+    #[VariantInfo(name = "None", description = "Every pixel uses the closest value")]
+    None,
+    #[VariantInfo(
+        name = "Floyd-Steinberg",
+        description = "Error diffusion to the neighbouring pixels"
+    )]
+    FloydSteinberg,
+    #[VariantInfo(name = "Ordered", description = "4x4 Bayer matrix")]
+    Ordered,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum ColorDepth {
+    #[VariantInfo(name = "True color", description = "RGB colors")]
+    TrueColor,
+    #[VariantInfo(name = "16 colors", description = "The 16 console colors")]
+    Colors16,
+}
+
+#[derive(Copy, Clone)]
+pub struct ImageImportOptions {
+    pub strategy: ImageStrategy,
+    pub width: u32,
+    pub height: u32,
+    // when set, the height is computed from the width (cells are about twice as tall as they are wide)
+    pub keep_aspect: bool,
+    pub colors: ColorDepth,
+    pub dithering: Dithering,
+}
+
+// characters from the darkest to the brightest
+const RAMP: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+// quadrant characters indexed by a mask of (upper left = 1, upper right = 2, lower left = 4, lower right = 8)
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+type Rgb = [f32; 3];
+
+// decoded image with its pixels blended over a black background
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+pub fn load_png(data: &[u8]) -> Result<Image, String> {
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(data));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err("unsupported indexed color image".to_string()),
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    if width == 0 || height == 0 {
+        return Err("the image is empty".to_string());
+    }
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let line = &buffer[y * info.line_size..];
+        for x in 0..width {
+            let p = &line[x * channels..(x + 1) * channels];
+            let (rgb, alpha) = match channels {
+                1 => ([p[0]; 3], 255),
+                2 => ([p[0]; 3], p[1]),
+                3 => ([p[0], p[1], p[2]], 255),
+                _ => ([p[0], p[1], p[2]], p[3]),
+            };
+            let a = alpha as f32 / 255.0;
+            pixels.push(rgb.map(|v| v as f32 * a));
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// resamples the image to the given size by averaging the source pixels covered by every destination pixel
+fn resample(image: &Image, width: usize, height: usize) -> Vec<Rgb> {
+    let mut result = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let y0 = y * image.height / height;
+        let y1 = ((y + 1) * image.height / height).max(y0 + 1);
+        for x in 0..width {
+            let x0 = x * image.width / width;
+            let x1 = ((x + 1) * image.width / width).max(x0 + 1);
+            let mut sum = [0.0; 3];
+            for sy in y0..y1 {
+                for sx in x0..x1 {
+                    let p = image.pixels[sy * image.width + sx];
+                    for c in 0..3 {
+                        sum[c] += p[c];
+                    }
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as f32;
+            result.push(sum.map(|v| v / count));
+        }
+    }
+    result
+}
+
+// replaces every pixel with its quantized value; `spread` is the distance between two quantization levels
+fn dither(
+    pixels: &mut [Rgb],
+    width: usize,
+    dithering: Dithering,
+    spread: f32,
+    quantize: impl Fn(Rgb) -> Rgb,
+) {
+    let height = pixels.len() / width;
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let old = pixels[i];
+            let new = match dithering {
+                Dithering::Ordered => {
+                    let offset = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+                    quantize(old.map(|v| v + offset * spread))
+                }
+                _ => quantize(old),
+            };
+            pixels[i] = new;
+            if dithering != Dithering::FloydSteinberg {
+                continue;
+            }
+            let error = [old[0] - new[0], old[1] - new[1], old[2] - new[2]];
+            let mut spread_error = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                if nx < 0 || nx >= width as isize || y + dy >= height {
+                    return;
+                }
+                let p = &mut pixels[(y + dy) * width + nx as usize];
+                for c in 0..3 {
+                    p[c] += error[c] * weight;
+                }
+            };
+            spread_error(1, 0, 7.0 / 16.0);
+            spread_error(-1, 1, 3.0 / 16.0);
+            spread_error(0, 1, 5.0 / 16.0);
+            spread_error(1, 1, 1.0 / 16.0);
+        }
+    }
+}
+
+fn to_u8(rgb: Rgb) -> (u8, u8, u8) {
+    let c = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    (c(rgb[0]), c(rgb[1]), c(rgb[2]))
+}
+
+fn luminance(rgb: Rgb) -> f32 {
+    0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]
+}
+
+fn nearest_palette(rgb: Rgb) -> Rgb {
+    let idx = colors::index(colors::nearest(to_u8(rgb))).unwrap_or(0);
+    let (r, g, b) = colors::PALETTE[idx];
+    [r as f32, g as f32, b as f32]
+}
+
+fn to_color(rgb: Rgb, depth: ColorDepth) -> Color {
+    match depth {
+        ColorDepth::TrueColor => {
+            let (r, g, b) = to_u8(rgb);
+            Color::RGB(r, g, b)
+        }
+        ColorDepth::Colors16 => colors::nearest(to_u8(rgb)),
+    }
+}
+
+fn average(pixels: &[Rgb]) -> Rgb {
+    let mut sum = [0.0; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += p[c];
+        }
+    }
+    sum.map(|v| v / pixels.len().max(1) as f32)
+}
+
+pub fn convert(image: &Image, options: &ImageImportOptions) -> Surface {
+    let cols = options.width.max(1) as usize;
+    let rows = if options.keep_aspect {
+        ((cols * image.height) as f32 / image.width as f32 / 2.0).round() as usize
+    } else {
+        options.height as usize
+    }
+    .max(1);
+    // number of image pixels (horizontally, vertically) represented by a single cell
+    let (sx, sy) = match options.strategy {
+        ImageStrategy::Ramp => (1, 1),
+        ImageStrategy::HalfBlock => (1, 2),
+        ImageStrategy::Quadrant => (2, 2),
+    };
+    let (width, height) = (cols * sx, rows * sy);
+    let mut pixels = resample(image, width, height);
+    let mut surface = Surface::new(cols as u32, rows as u32);
+    match options.strategy {
+        ImageStrategy::Ramp => {
+            let levels = (RAMP.len() - 1) as f32;
+            let spread = 255.0 / levels;
+            let mut lum: Vec<Rgb> = pixels.iter().map(|p| [luminance(*p); 3]).collect();
+            dither(&mut lum, width, options.dithering, spread, |v| {
+                v.map(|l| (l / spread).round().clamp(0.0, levels) * spread)
+            });
+            for y in 0..rows {
+                for x in 0..cols {
+                    let i = y * width + x;
+                    let ch = RAMP[(lum[i][0] / spread).round() as usize];
+                    let fore = to_color(pixels[i], options.colors);
+                    surface.write_char(
+                        x as i32,
+                        y as i32,
+                        Character::new(ch, fore, Color::Transparent, CharFlags::None),
+                    );
+                }
+            }
+        }
+        ImageStrategy::HalfBlock | ImageStrategy::Quadrant => {
+            if options.colors == ColorDepth::Colors16 {
+                dither(
+                    &mut pixels,
+                    width,
+                    options.dithering,
+                    128.0,
+                    nearest_palette,
+                );
+            }
+            for y in 0..rows {
+                for x in 0..cols {
+                    let cell: Vec<Rgb> = (0..sy)
+                        .flat_map(|dy| (0..sx).map(move |dx| (dx, dy)))
+                        .map(|(dx, dy)| pixels[(y * sy + dy) * width + x * sx + dx])
+                        .collect();
+                    let ch = if options.strategy == ImageStrategy::HalfBlock {
+                        Character::new(
+                            '▀',
+                            to_color(cell[0], options.colors),
+                            to_color(cell[1], options.colors),
+                            CharFlags::None,
+                        )
+                    } else {
+                        // pixels brighter than the cell average use the foreground color
+                        let mean = luminance(average(&cell));
+                        let mut mask = 0;
+                        for (i, p) in cell.iter().enumerate() {
+                            if luminance(*p) > mean {
+                                mask |= 1 << i;
+                            }
+                        }
+                        let fore: Vec<Rgb> = (0..4)
+                            .filter(|i| mask & (1 << i) != 0)
+                            .map(|i| cell[i])
+                            .collect();
+                        let back: Vec<Rgb> = (0..4)
+                            .filter(|i| mask & (1 << i) == 0)
+                            .map(|i| cell[i])
+                            .collect();
+                        Character::new(
+                            QUADRANTS[mask],
+                            to_color(average(&fore), options.colors),
+                            to_color(average(&back), options.colors),
+                            CharFlags::None,
+                        )
+                    };
+                    surface.write_char(x as i32, y as i32, ch);
+                }
+            }
+        }
+    }
+    surface
+}
+
+#[ModalWindow(events = ButtonEvents, response = ImageImportOptions)]
+pub struct ImageImportDialog {
+    strategy: Handle<Selector<ImageStrategy>>,
+    width: Handle<NumericSelector<u32>>,
+    height: Handle<NumericSelector<u32>>,
+    keep_aspect: Handle<CheckBox>,
+    colors: Handle<Selector<ColorDepth>>,
+    dithering: Handle<Selector<Dithering>>,
+    import: Handle<Button>,
+}
+
+impl ImageImportDialog {
+    pub fn new() -> Self {
+        let mut w = Self {
+            base: ModalWindow::new(
+                "Import image",
+                layout!("a:c,w:44,h:17"),
+                window::Flags::None,
+            ),
+            strategy: Handle::None,
+            width: Handle::None,
+            height: Handle::None,
+            keep_aspect: Handle::None,
+            colors: Handle::None,
+            dithering: Handle::None,
+            import: Handle::None,
+        };
+        w.add(label!("'Strategy:',x:1,y:1,w:14,h:1"));
+        w.strategy = w.add(selector!("ImageStrategy,x:16,y:1,w:25,value:HalfBlock"));
+        w.add(label!("'Width:',x:1,y:3,w:14,h:1"));
+        w.width = w.add(numericselector!(
+            "u32,value:80,min:1,max:1000,step:1,x:16,y:3,w:25"
+        ));
+        w.add(label!("'Height:',x:1,y:5,w:14,h:1"));
+        w.height = w.add(numericselector!(
+            "u32,value:25,min:1,max:1000,step:1,x:16,y:5,w:25"
+        ));
+        w.keep_aspect = w.add(checkbox!(
+            "'Keep aspect ratio (ignore height)',x:1,y:7,w:40,h:1,checked:true"
+        ));
+        w.add(label!("'Colors:',x:1,y:9,w:14,h:1"));
+        w.colors = w.add(selector!("ColorDepth,x:16,y:9,w:25,value:TrueColor"));
+        w.add(label!("'Dithering:',x:1,y:11,w:14,h:1"));
+        w.dithering = w.add(selector!("Dithering,x:16,y:11,w:25,value:None"));
+        w.import = w.add(button!("&Import,x:8,y:13,w:12"));
+        w.add(button!("&Cancel,x:22,y:13,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for ImageImportDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.import {
+            let options = ImageImportOptions {
+                strategy: self.control(self.strategy).unwrap().value(),
+                width: self.control(self.width).unwrap().value(),
+                height: self.control(self.height).unwrap().value(),
+                keep_aspect: self.control(self.keep_aspect).unwrap().is_checked(),
+                colors: self.control(self.colors).unwrap().value(),
+                dithering: self.control(self.dithering).unwrap().value(),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
@@ -0,0 +1,327 @@
+use appcui::prelude::*;
+
+use crate::colors;
+
+#[derive(Clone)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    // 0..=100
+    pub opacity: u8,
+    pub locked: bool,
+    pub cells: Cells,
+}
+
+impl Layer {
+    // a layer where every cell is transparent
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        Self::with_cells(name, Cells::new(width, height, EMPTY))
+    }
+
+    pub fn with_surface(name: &str, surface: Surface) -> Self {
+        Self::with_cells(name, Cells::from_surface(&surface))
+    }
+
+    pub fn with_cells(name: &str, cells: Cells) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            opacity: 100,
+            locked: false,
+            cells,
+        }
+    }
+}
+
+pub const EMPTY: Character = Character {
+    code: '\0',
+    foreground: Color::Transparent,
+    background: Color::Transparent,
+    flags: CharFlags::None,
+};
+
+// The cells of a layer. `Surface::write_char` keeps the existing code and colors when the new ones are
+// null / transparent, so a layer keeps its own cells: `set` stores a cell exactly as it is given, while
+// `write_char` works like the `Surface` one.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cells {
+    size: Size,
+    cells: Vec<Character>,
+}
+
+impl Cells {
+    pub fn new(width: u32, height: u32, fill: Character) -> Self {
+        Self {
+            size: Size::new(width, height),
+            cells: vec![fill; (width * height) as usize],
+        }
+    }
+
+    pub fn from_surface(surface: &Surface) -> Self {
+        let size = surface.size();
+        let cells = (0..size.height as i32)
+            .flat_map(|y| (0..size.width as i32).map(move |x| (x, y)))
+            .map(|(x, y)| surface.char(x, y).copied().unwrap_or(EMPTY))
+            .collect();
+        Self { size, cells }
+    }
+
+    pub fn to_surface(&self) -> Surface {
+        crop_surface(
+            self,
+            Rect::with_point_and_size(Point::new(0, 0), self.size),
+            EMPTY,
+        )
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.size.width as i32 || y >= self.size.height as i32 {
+            return None;
+        }
+        Some((y * self.size.width as i32 + x) as usize)
+    }
+
+    pub fn char(&self, x: i32, y: i32) -> Option<&Character> {
+        self.index(x, y).map(|i| &self.cells[i])
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, ch: Character) {
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = ch;
+        }
+    }
+
+    // sets every cell of `rect` to `ch` (exactly, see `set`)
+    pub fn set_rect(&mut self, rect: Rect, ch: Character) {
+        for y in rect.top()..=rect.bottom() {
+            for x in rect.left()..=rect.right() {
+                self.set(x, y, ch);
+            }
+        }
+    }
+
+    pub fn write_char(&mut self, x: i32, y: i32, ch: Character) {
+        if let Some(i) = self.index(x, y) {
+            self.cells[i].set(ch);
+        }
+    }
+
+    // the part of `rect` that is inside of the layer
+    fn clip(&self, rect: Rect) -> Option<Rect> {
+        let (left, top) = (rect.left().max(0), rect.top().max(0));
+        let right = rect.right().min(self.size.width as i32 - 1);
+        let bottom = rect.bottom().min(self.size.height as i32 - 1);
+        (left <= right && top <= bottom).then(|| Rect::new(left, top, right, bottom))
+    }
+
+    // Paints with the `Surface` methods on the `rect` part of the layer only: `paint` gets a surface that
+    // holds a copy of that part and uses the coordinates of the layer. Cells outside of `rect` are not
+    // changed.
+    pub fn draw(&mut self, rect: Rect, paint: impl FnOnce(&mut Surface)) {
+        let Some(rect) = self.clip(rect) else {
+            return;
+        };
+        let mut surface = crop_surface(self, rect, EMPTY);
+        surface.set_origin(-rect.left(), -rect.top());
+        paint(&mut surface);
+        surface.reset_origin();
+        for y in 0..rect.height() as i32 {
+            for x in 0..rect.width() as i32 {
+                if let Some(ch) = surface.char(x, y) {
+                    self.set(rect.left() + x, rect.top() + y, *ch);
+                }
+            }
+        }
+    }
+
+    // the cells of `rect` row by row (cells outside of the layer are empty)
+    pub fn region(&self, rect: Rect) -> Vec<Character> {
+        (rect.top()..=rect.bottom())
+            .flat_map(|y| (rect.left()..=rect.right()).map(move |x| (x, y)))
+            .map(|(x, y)| self.char(x, y).copied().unwrap_or(EMPTY))
+            .collect()
+    }
+
+    // replaces the `rect` part of the layer with `cells` (given row by row, see `region`)
+    pub fn patch(&mut self, rect: Rect, cells: &[Character]) {
+        let width = rect.width() as usize;
+        for (i, ch) in cells.iter().enumerate() {
+            let (x, y) = ((i % width) as i32, (i / width) as i32);
+            self.set(rect.left() + x, rect.top() + y, *ch);
+        }
+    }
+
+    // the `rect` part of the layer (cells outside of the layer are set to `fill`)
+    pub fn crop(&self, rect: Rect, fill: Character) -> Cells {
+        let cells = (rect.top()..=rect.bottom())
+            .flat_map(|y| (rect.left()..=rect.right()).map(move |x| (x, y)))
+            .map(|(x, y)| self.char(x, y).copied().unwrap_or(fill))
+            .collect();
+        Self {
+            size: rect.size(),
+            cells,
+        }
+    }
+}
+
+// `Surface::write_char` keeps the existing code and colors when the new ones are null / transparent,
+// so surfaces that must hold such cells are built from their serialized (.srf) form
+pub fn surface_from_cells(
+    width: u32,
+    height: u32,
+    cell: impl Fn(i32, i32) -> Character,
+) -> Surface {
+    let mut buffer = Vec::with_capacity(12 + (width * height) as usize * 8);
+    buffer.extend_from_slice(b"SRF");
+    buffer.push(1);
+    buffer.extend_from_slice(&width.to_le_bytes());
+    buffer.extend_from_slice(&height.to_le_bytes());
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let ch = cell(x, y);
+            buffer.extend_from_slice(&(ch.code as u32).to_le_bytes());
+            buffer.extend_from_slice(&ch.flags.get_value().to_le_bytes());
+            for color in [ch.foreground, ch.background] {
+                match color {
+                    Color::Transparent => buffer.push(16),
+                    Color::RGB(r, g, b) => buffer.extend_from_slice(&[17, r, g, b]),
+                    _ => buffer.push(colors::index(color).unwrap_or(0) as u8),
+                }
+            }
+        }
+    }
+    Surface::from_buffer(&buffer).unwrap_or_else(|_| Surface::new(width, height))
+}
+
+pub fn transparent_surface(width: u32, height: u32) -> Surface {
+    surface_from_cells(width, height, |_, _| EMPTY)
+}
+
+// the `rect` part of the layer as a surface (cells outside of the layer are set to `fill`)
+pub fn crop_surface(cells: &Cells, rect: Rect, fill: Character) -> Surface {
+    surface_from_cells(rect.width(), rect.height(), |x, y| {
+        cells
+            .char(rect.left() + x, rect.top() + y)
+            .copied()
+            .unwrap_or(fill)
+    })
+}
+
+// the smallest rectangle that contains both rectangles
+pub fn union(a: Rect, b: Rect) -> Rect {
+    Rect::new(
+        a.left().min(b.left()),
+        a.top().min(b.top()),
+        a.right().max(b.right()),
+        a.bottom().max(b.bottom()),
+    )
+}
+
+// the null code and the transparent colors of every cell are taken from `fill`
+pub fn fill_transparent(cells: &mut Cells, fill: Character) {
+    for ch in cells.cells.iter_mut() {
+        if ch.code == '\0' {
+            ch.code = fill.code;
+            ch.flags = fill.flags;
+        }
+        if ch.foreground == Color::Transparent {
+            ch.foreground = fill.foreground;
+        }
+        if ch.background == Color::Transparent {
+            ch.background = fill.background;
+        }
+    }
+}
+
+// crops every layer to `rect`; the new area is filled with `fill` on the bottom layer and left transparent
+// on the others
+pub fn crop_layers(layers: &mut [Layer], rect: Rect, fill: Character) {
+    for (index, layer) in layers.iter_mut().enumerate() {
+        let fill = if index == 0 { fill } else { EMPTY };
+        layer.cells = layer.cells.crop(rect, fill);
+    }
+}
+
+fn blend(below: Color, above: Color, opacity: u8) -> Color {
+    if above == Color::Transparent {
+        return below;
+    }
+    match (colors::rgb(below), colors::rgb(above)) {
+        (Some(b), Some(a)) if opacity < 100 => {
+            let mix = |b: u8, a: u8| {
+                ((b as u32 * (100 - opacity as u32) + a as u32 * opacity as u32) / 100) as u8
+            };
+            Color::RGB(mix(b.0, a.0), mix(b.1, a.1), mix(b.2, a.2))
+        }
+        _ => above,
+    }
+}
+
+// the cell seen when `above` (from a layer with the given opacity) is placed over `below`
+fn place(below: Character, above: Character, opacity: u8) -> Character {
+    if opacity == 0 {
+        return below;
+    }
+    let mut result = below;
+    if above.code != '\0' && opacity >= 50 {
+        result.code = above.code;
+        result.flags = above.flags;
+    }
+    result.foreground = blend(below.foreground, above.foreground, opacity);
+    result.background = blend(below.background, above.background, opacity);
+    result
+}
+
+// composed cell of all visible layers (layers are ordered from bottom to top)
+pub fn cell(layers: &[Layer], x: i32, y: i32) -> Option<Character> {
+    let mut result = None;
+    for layer in layers.iter().filter(|l| l.visible) {
+        if let Some(ch) = layer.cells.char(x, y) {
+            result = Some(place(result.unwrap_or(EMPTY), *ch, layer.opacity));
+        }
+    }
+    result
+}
+
+// draws the `area` part of the composed layers on `surface`, with the top-left corner of the canvas at
+// (`x`, `y`)
+pub fn draw(layers: &[Layer], surface: &mut Surface, x: i32, y: i32, area: Rect) {
+    for cy in area.top()..=area.bottom() {
+        for cx in area.left()..=area.right() {
+            if let Some(ch) = cell(layers, cx, cy) {
+                surface.write_char(x + cx, y + cy, ch);
+            }
+        }
+    }
+}
+
+pub fn flatten(layers: &[Layer]) -> Surface {
+    let size = layers
+        .first()
+        .map(|l| l.cells.size())
+        .unwrap_or(Size::new(1, 1));
+    surface_from_cells(size.width, size.height, |x, y| {
+        cell(layers, x, y).unwrap_or(EMPTY)
+    })
+}
+
+// draws `upper` over `lower` (used to merge a layer into the one below it)
+pub fn merge(lower: &Layer, upper: &Layer) -> Cells {
+    let mut merged = lower.cells.clone();
+    if upper.visible {
+        for (below, above) in merged.cells.iter_mut().zip(upper.cells.cells.iter()) {
+            *below = place(*below, *above, upper.opacity);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+// every cell of the layer, row by row
+pub(crate) fn cells(layer: &Layer) -> Vec<Character> {
+    layer.cells.cells.clone()
+}
@@ -0,0 +1,6 @@
+// The painting model (layers, their colors and the undo history), shared by the application and the
+// benchmarks.
+
+pub mod colors;
+pub mod history;
+pub mod layer;
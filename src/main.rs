@@ -1,3 +1,4 @@
+use std::path::Path;
 use appcui::prelude::*;
 mod painter_window;
 use painter_window::PainterWindow;
@@ -6,14 +7,40 @@ mod selection;
 use selection::Selection;
 mod drawing_object;
 use drawing_object::DrawingObject;
+mod box_join;
+use ascii_painter::colors;
+mod ansi;
+use ansi::AnsiColors;
+mod text_export;
+use text_export::TextExportDialog;
+mod html_export;
+use html_export::HtmlExportDialog;
+mod svg_export;
+use svg_export::SvgExportDialog;
+mod bitmap_font;
+mod png_export;
+use png_export::PngExportDialog;
+mod image_import;
+use image_import::ImageImportDialog;
+mod project;
+use project::DocumentPropertiesDialog;
+use ascii_painter::layer;
+use ascii_painter::history;
+mod canvas_size;
+use canvas_size::CanvasSizeDialog;
+mod new_document;
+use new_document::NewDocumentDialog;
+mod transform;
+mod palette_bar;
 use appcui::dialogs::{OpenFileDialogFlags, SaveFileDialogFlags};
 
 #[Desktop(events = [MenuEvents, DesktopEvents],  
           overwrite = OnPaint,
-          commands = [New, Exit, Open, Save])]
+          commands = [New, Exit, Open, Save, SaveTemplate, Properties, ImportImage, ExportAnsi16, ExportAnsi256, ExportAnsiTrueColor, ExportText, ExportHtml, ExportSvg, ExportPng, CanvasSize, CropToSelection, TrimToContent])]
 struct PainterDesktop {
     index: u32,
     menu_file: Handle<Menu>,
+    menu_image: Handle<Menu>,
 }
 
 impl PainterDesktop {
@@ -22,6 +49,74 @@ impl PainterDesktop {
             base: Desktop::new(),
             index: 1,
             menu_file: Handle::None,
+            menu_image: Handle::None,
+        }
+    }
+
+    fn open_ansi(&mut self, file: &Path) {
+        let data = match std::fs::read(file) {
+            Ok(data) => data,
+            Err(err) => {
+                dialogs::error("Error", &format!("Failed to read '{}': {}", file.display(), err));
+                return;
+            }
+        };
+        let result = ansi::import(&data);
+        let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "ANSI".to_string());
+        self.add_window(PainterWindow::from_surface(&name, result.surface));
+        if !result.skipped.is_empty() {
+            let mut report = String::from("The following unsupported sequences were skipped:\n");
+            for (sequence, count) in result.skipped.iter() {
+                report.push_str(&format!("\n{} ({} times)", sequence, count));
+            }
+            dialogs::message("ANSI import", &report);
+        }
+    }
+
+    fn import_image(&mut self, file: &Path) {
+        let image = match std::fs::read(file).map_err(|e| e.to_string()).and_then(|data| image_import::load_png(&data)) {
+            Ok(image) => image,
+            Err(err) => {
+                dialogs::error("Error", &format!("Failed to load '{}': {}", file.display(), err));
+                return;
+            }
+        };
+        if let Some(options) = ImageImportDialog::new().show() {
+            let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "Image".to_string());
+            self.add_window(PainterWindow::from_surface(&name, image_import::convert(&image, &options)));
+        }
+    }
+
+    fn add_painter(&mut self, painter: PainterWindow) {
+        let h = self.add_window(painter);
+        if let Some(w) = self.window_mut(h) {
+            w.restore_tool_settings();
+        }
+    }
+
+    fn active_painter(&self) -> Option<Handle<PainterWindow>> {
+        self.active_window_handle().map(|window| unsafe { window.unsafe_cast() })
+    }
+
+    fn export(&mut self, title: &str, mask: &str, convert: impl Fn(&Surface) -> Result<Vec<u8>, String>) {
+        let Some(window) = self.active_window_handle() else {
+            return;
+        };
+        let h: Handle<PainterWindow> = unsafe { window.unsafe_cast() };
+        let Some(content) = self.window_mut(h).and_then(|w| w.surface()).map(|s| convert(&s)) else {
+            return;
+        };
+        let content = match content {
+            Ok(content) => content,
+            Err(err) => {
+                dialogs::error("Error", &format!("Failed to export the painting: {}", err));
+                return;
+            }
+        };
+        if let Some(path) = dialogs::save(title, "", dialogs::Location::Last, Some(mask), SaveFileDialogFlags::Icons|SaveFileDialogFlags::ValidateOverwrite) {
+            if let Err(err) = std::fs::write(&path, content) {
+                dialogs::error("Error", &format!("Failed to export to '{}': {}", path.display(), err));
+            }
         }
     }
 }
@@ -39,10 +134,30 @@ impl DesktopEvents for PainterDesktop {
                 {'&New',cmd: New},
                 {'&Open',cmd: Open},
                 {'&Save',cmd: Save},
+                {'Save as te&mplate',cmd: SaveTemplate},
+                {'&Document properties',cmd: Properties},
+                {'&Import image',cmd: ImportImage},
+                {-},
+                {'Export as &ANSI',items:[
+                    {'&16 colors',cmd: ExportAnsi16},
+                    {'&256 colors',cmd: ExportAnsi256},
+                    {'&True color',cmd: ExportAnsiTrueColor}
+                ]},
+                {'Export as &text',cmd: ExportText},
+                {'Export as &HTML',cmd: ExportHtml},
+                {'Export as S&VG',cmd: ExportSvg},
+                {'Export as &PNG',cmd: ExportPng},
                 {-},
                 {'E&xit',cmd: Exit}
             ]
         "));
+        self.menu_image = self.register_menu(menu!("
+            &Image,class: PainterDesktop, items:[
+                {'&Canvas size',cmd: CanvasSize},
+                {'C&rop to selection',cmd: CropToSelection},
+                {'&Trim to content',cmd: TrimToContent}
+            ]
+        "));
     }
 }
 
@@ -50,22 +165,36 @@ impl MenuEvents for PainterDesktop {
     fn on_command(&mut self, _menu: Handle<Menu>, _item: Handle<menu::Command>, command: painterdesktop::Commands) {
         match command {
             painterdesktop::Commands::New => {
+                let Some(options) = NewDocumentDialog::new().show() else {
+                    return;
+                };
                 let name = format!("Painting─{}", self.index);
                 self.index += 1;
-                self.add_window(PainterWindow::new(&name));
+                if let Some(template) = &options.template {
+                    let size = Size::new(options.width, options.height);
+                    match PainterWindow::from_template(&name, template, size, options.background) {
+                        Ok(painter) => self.add_painter(painter),
+                        Err(err) => dialogs::error("Error", &format!("Failed to open template '{}': {}", template.display(), err)),
+                    }
+                } else {
+                    self.add_window(PainterWindow::new(&name, options.width, options.height, options.background));
+                }
             }
             painterdesktop::Commands::Exit => self.close(),
             painterdesktop::Commands::Open => {
-                if let Some(file) = dialogs::open("Open surfaces", "", dialogs::Location::Last, Some("Surface image = [srf]"), OpenFileDialogFlags::Icons) {
-                    if let Ok(painter) = PainterWindow::from_file(&file) {
-                        self.add_window(painter);
+                if let Some(file) = dialogs::open("Open surfaces", "", dialogs::Location::Last, Some("Project = [apj], Surface image = [srf], ANSI art = [ans]"), OpenFileDialogFlags::Icons) {
+                    if file.extension().is_some_and(|e| e.eq_ignore_ascii_case("ans")) {
+                        self.open_ansi(&file);
                     } else {
-                        dialogs::error("Error", "Failed to open the painting file.");
+                        match PainterWindow::from_file(&file) {
+                            Ok(painter) => self.add_painter(painter),
+                            Err(err) => dialogs::error("Error", &format!("Failed to open '{}': {}", file.display(), err)),
+                        }
                     }
                 }
             }
             painterdesktop::Commands::Save => {
-                if let Some(path) = dialogs::save("Save surface", "", dialogs::Location::Last, Some("Surface image = [srf]"),SaveFileDialogFlags::Icons|SaveFileDialogFlags::ValidateOverwrite) {
+                if let Some(path) = dialogs::save("Save surface", "", dialogs::Location::Last, Some("Project = [apj], Surface image = [srf]"),SaveFileDialogFlags::Icons|SaveFileDialogFlags::ValidateOverwrite) {
                     if let Some(window) = self.active_window_handle() {
                         let h: Handle<PainterWindow> = unsafe { window.unsafe_cast() };
                         if let Some(painter) = self.window_mut(h) {
@@ -76,11 +205,101 @@ impl MenuEvents for PainterDesktop {
                     }
                 }
             }
+            painterdesktop::Commands::SaveTemplate => {
+                let Some(h) = self.active_painter() else {
+                    return;
+                };
+                let Some(dir) = new_document::templates_dir() else {
+                    dialogs::error("Error", "The templates folder could not be located");
+                    return;
+                };
+                if let Err(err) = std::fs::create_dir_all(&dir) {
+                    dialogs::error("Error", &format!("Failed to create '{}': {}", dir.display(), err));
+                    return;
+                }
+                if let Some(path) = dialogs::save("Save as template", "", dialogs::Location::Path(&dir), Some("Project = [apj]"), SaveFileDialogFlags::Icons|SaveFileDialogFlags::ValidateOverwrite) {
+                    if let Some(painter) = self.window_mut(h) {
+                        if let Err(err) = painter.save_to_file(&path) {
+                            dialogs::error("Error", &format!("Failed to save the template: {}", err));
+                        }
+                    }
+                }
+            }
+            painterdesktop::Commands::Properties => {
+                if let Some(window) = self.active_window_handle() {
+                    let h: Handle<PainterWindow> = unsafe { window.unsafe_cast() };
+                    let info = self.window_mut(h).map(|w| w.document_info().clone());
+                    if let Some(info) = info.and_then(|info| DocumentPropertiesDialog::new(&info).show()) {
+                        if let Some(painter) = self.window_mut(h) {
+                            painter.set_document_info(info);
+                        }
+                    }
+                }
+            }
+            painterdesktop::Commands::ImportImage => {
+                if let Some(file) = dialogs::open("Import image", "", dialogs::Location::Last, Some("PNG image = [png]"), OpenFileDialogFlags::Icons) {
+                    self.import_image(&file);
+                }
+            }
+            painterdesktop::Commands::ExportAnsi16 => {
+                self.export("Export as ANSI", "ANSI art = [ans,txt]", |s| Ok(ansi::export(s, AnsiColors::Colors16).into_bytes()));
+            }
+            painterdesktop::Commands::ExportAnsi256 => {
+                self.export("Export as ANSI", "ANSI art = [ans,txt]", |s| Ok(ansi::export(s, AnsiColors::Colors256).into_bytes()));
+            }
+            painterdesktop::Commands::ExportAnsiTrueColor => {
+                self.export("Export as ANSI", "ANSI art = [ans,txt]", |s| Ok(ansi::export(s, AnsiColors::TrueColor).into_bytes()));
+            }
+            painterdesktop::Commands::ExportText => {
+                if let Some(options) = TextExportDialog::new().show() {
+                    self.export("Export as text", "Text = [txt,md]", |s| Ok(text_export::export(s, &options).into_bytes()));
+                }
+            }
+            painterdesktop::Commands::ExportHtml => {
+                if let Some(options) = HtmlExportDialog::new().show() {
+                    self.export("Export as HTML", "HTML page = [html,htm]", |s| Ok(html_export::export(s, &options).into_bytes()));
+                }
+            }
+            painterdesktop::Commands::ExportSvg => {
+                if let Some(options) = SvgExportDialog::new().show() {
+                    self.export("Export as SVG", "SVG image = [svg]", |s| Ok(svg_export::export(s, &options).into_bytes()));
+                }
+            }
+            painterdesktop::Commands::ExportPng => {
+                if let Some(options) = PngExportDialog::new().show() {
+                    self.export("Export as PNG", "PNG image = [png]", |s| png_export::export(s, &options));
+                }
+            }
+            painterdesktop::Commands::CanvasSize => {
+                if let Some(h) = self.active_painter() {
+                    let size = self.window_mut(h).and_then(|w| w.canvas_size());
+                    if let Some(options) = size.and_then(|size| CanvasSizeDialog::new(size).show()) {
+                        if let Some(painter) = self.window_mut(h) {
+                            painter.resize_canvas(&options);
+                        }
+                    }
+                }
+            }
+            painterdesktop::Commands::CropToSelection => {
+                if let Some(h) = self.active_painter() {
+                    if self.window_mut(h).is_some_and(|w| !w.crop_to_selection()) {
+                        dialogs::message("Crop to selection", "Select the area to keep with the Selection tool first.");
+                    }
+                }
+            }
+            painterdesktop::Commands::TrimToContent => {
+                if let Some(h) = self.active_painter() {
+                    if let Some(painter) = self.window_mut(h) {
+                        painter.trim_to_content();
+                    }
+                }
+            }
         }
     }
 
     fn on_update_menubar(&self, menubar: &mut MenuBar) {
         menubar.add(self.menu_file, 0);
+        menubar.add(self.menu_image, 2);
     }
 }
 
@@ -0,0 +1,171 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use appcui::prelude::*;
+
+use crate::project;
+
+pub struct NewDocumentOptions {
+    pub width: u32,
+    pub height: u32,
+    pub background: Character,
+    // the painting (.apj / .srf) a custom template starts from
+    pub template: Option<PathBuf>,
+}
+
+struct Template {
+    name: String,
+    width: u32,
+    height: u32,
+    path: Option<PathBuf>,
+}
+
+impl Template {
+    fn builtin(name: &str, width: u32, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            path: None,
+        }
+    }
+}
+
+// folder with the user's templates (~/.asciipainter/templates)
+pub fn templates_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".asciipainter").join("templates"))
+}
+
+// only the header is read, files that can not be read are skipped
+fn canvas_size(path: &Path) -> Option<(u32, u32)> {
+    let mut file = std::fs::File::open(path).ok()?;
+    project::read_canvas_size(&mut file).ok()
+}
+
+fn custom_templates() -> Vec<Template> {
+    let Some(entries) = templates_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<Template> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("apj") || e.eq_ignore_ascii_case("srf"))
+        })
+        .filter_map(|path| {
+            let (width, height) = canvas_size(&path)?;
+            Some(Template {
+                name: path.file_stem()?.to_string_lossy().to_string(),
+                width,
+                height,
+                path: Some(path),
+            })
+        })
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+#[ModalWindow(events = ButtonEvents + ListBoxEvents, response = NewDocumentOptions)]
+pub struct NewDocumentDialog {
+    templates: Vec<Template>,
+    list: Handle<ListBox>,
+    width: Handle<NumericSelector<u32>>,
+    height: Handle<NumericSelector<u32>>,
+    back_char: Handle<CharPicker>,
+    back_fore: Handle<ColorPicker>,
+    back_back: Handle<ColorPicker>,
+    create: Handle<Button>,
+}
+
+impl NewDocumentDialog {
+    pub fn new() -> Self {
+        let mut templates = vec![
+            Template::builtin("Blank", 100, 100),
+            Template::builtin("Terminal (80x24)", 80, 24),
+            Template::builtin("DOS (80x25)", 80, 25),
+            Template::builtin("Large terminal (132x43)", 132, 43),
+        ];
+        templates.extend(custom_templates());
+        let mut w = Self {
+            base: ModalWindow::new(
+                "New painting",
+                layout!("a:c,w:50,h:19"),
+                window::Flags::None,
+            ),
+            templates,
+            list: Handle::None,
+            width: Handle::None,
+            height: Handle::None,
+            back_char: Handle::None,
+            back_fore: Handle::None,
+            back_back: Handle::None,
+            create: Handle::None,
+        };
+        w.add(label!("'Template:',x:1,y:1,w:10,h:1"));
+        let mut list = listbox!("x:12,y:1,w:34,h:5,flags: ScrollBars");
+        for template in w.templates.iter() {
+            list.add(&template.name);
+        }
+        w.list = w.add(list);
+        w.add(label!("'Width:',x:1,y:7,w:10,h:1"));
+        w.width = w.add(numericselector!(
+            "u32,value:100,min:1,max:1000,step:1,x:12,y:7,w:34"
+        ));
+        w.add(label!("'Height:',x:1,y:9,w:10,h:1"));
+        w.height = w.add(numericselector!(
+            "u32,value:100,min:1,max:1000,step:1,x:12,y:9,w:34"
+        ));
+        w.add(label!("'Fill:',x:1,y:11,w:10,h:1"));
+        w.back_char = w.add(CharPicker::new(Some(' '), layout!("x:12,y:11,w:34")));
+        w.add(label!("'Fore:',x:1,y:13,w:10,h:1"));
+        w.back_fore = w.add(ColorPicker::new(Color::White, layout!("x:12,y:13,w:14")));
+        w.add(label!("'Back:',x:28,y:13,w:5,h:1"));
+        w.back_back = w.add(ColorPicker::new(Color::Black, layout!("x:34,y:13,w:12")));
+        w.create = w.add(button!("C&reate,x:11,y:15,w:12"));
+        w.add(button!("&Cancel,x:25,y:15,w:12"));
+        w
+    }
+}
+
+impl ListBoxEvents for NewDocumentDialog {
+    fn on_current_item_changed(&mut self, _: Handle<ListBox>, index: usize) -> EventProcessStatus {
+        let Some(template) = self.templates.get(index) else {
+            return EventProcessStatus::Ignored;
+        };
+        let (width, height) = (template.width, template.height);
+        let h = self.width;
+        if let Some(n) = self.control_mut(h) {
+            n.set_value(width);
+        }
+        let h = self.height;
+        if let Some(n) = self.control_mut(h) {
+            n.set_value(height);
+        }
+        EventProcessStatus::Processed
+    }
+}
+
+impl ButtonEvents for NewDocumentDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.create {
+            let index = self.control(self.list).unwrap().index();
+            let options = NewDocumentOptions {
+                width: self.control(self.width).unwrap().value(),
+                height: self.control(self.height).unwrap().value(),
+                background: Character::new(
+                    self.control(self.back_char).unwrap().char().unwrap_or(' '),
+                    self.control(self.back_fore).unwrap().color(),
+                    self.control(self.back_back).unwrap().color(),
+                    CharFlags::None,
+                ),
+                template: self.templates.get(index).and_then(|t| t.path.clone()),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
@@ -2,92 +2,172 @@ use std::path::Path;
 
 use appcui::prelude::*;
 
+use crate::box_join;
+use crate::canvas_size;
+use crate::drawing_object::ArrowHead;
+use crate::drawing_object::FillMatch;
+use crate::drawing_object::LineDirection;
 use crate::drawing_object::SelectionObject;
+use crate::history;
+use crate::history::Change;
+use crate::history::History;
+use crate::history::LayerProperties;
+use crate::layer;
+use crate::layer::Cells;
+use crate::layer::Layer;
+use crate::transform::Transform;
 
 use super::DrawingObject;
 use super::Selection;
 
-#[CustomControl(overwrite = OnPaint + OnMouseEvent + OnResize + OnKeyPressed)]
+// how the clipboard cells are written over the painting
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum PasteMode {
+    Opaque,
+    // spaces (and empty cells) leave the painting visible
+    SkipSpaces,
+    // cells that look like the clipboard's background (its most common character and back color) are
+    // skipped
+    SkipBackground,
+    // characters and fore colors are pasted, the painting keeps its back color
+    ForegroundOnly,
+}
+
+impl PasteMode {
+    // the cell written over `target` (`None` if the target is kept)
+    fn cell(
+        self,
+        ch: Character,
+        target: Option<&Character>,
+        background: (char, Color),
+    ) -> Option<Character> {
+        match self {
+            PasteMode::Opaque => Some(ch),
+            PasteMode::SkipSpaces if ch.code == ' ' || ch.code == '\0' => None,
+            PasteMode::SkipBackground if (ch.code, ch.background) == background => None,
+            PasteMode::ForegroundOnly => Some(Character {
+                background: target.map_or(ch.background, |t| t.background),
+                ..ch
+            }),
+            _ => Some(ch),
+        }
+    }
+}
+
+// the most common character and back color of a surface
+fn background_of(surface: &Surface) -> (char, Color) {
+    let size = surface.size();
+    let mut counts: Vec<((char, Color), usize)> = Vec::new();
+    for y in 0..size.height as i32 {
+        for x in 0..size.width as i32 {
+            let Some(ch) = surface.char(x, y) else {
+                continue;
+            };
+            let key = (ch.code, ch.background);
+            match counts.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((key, 1)),
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map_or((' ', Color::Black), |(key, _)| key)
+}
+
+#[CustomControl(overwrite = OnPaint + OnMouseEvent + OnResize + OnKeyPressed, emit = CharacterPicked + HistoryChanged)]
 pub struct PainterControl {
-    surface: Surface,
+    layers: Vec<Layer>,
+    active: usize,
     scrollbars: ScrollBars,
     selection: Selection,
     drawwing_object: DrawingObject,
     is_move_dragging: bool,
     move_drag_start: Point,
     move_drag_initial_offset: Point,
-    undo_stack: Vec<Surface>,
-    redo_stack: Vec<Surface>,
-    max_undo_levels: usize,
+    // an Alt+click sampled a cell: the drag / release that follow it are not sent to the tool
+    picking: bool,
+    history: History,
     clipboard: Option<Surface>,
+    paste_mode: PasteMode,
+    picked: Option<Character>,
 }
 
 impl PainterControl {
-    pub fn new(width: u32, height: u32) -> Self {
+    pub fn new(width: u32, height: u32, background: Character) -> Self {
+        let mut surface = Surface::new(width, height);
+        surface.clear(background);
+        Self::with_surface(surface)
+    }
+    pub fn with_surface(surface: Surface) -> Self {
+        Self::with_layers(vec![Layer::with_surface("Background", surface)])
+    }
+    // layers are ordered from bottom to top (the top one becomes the active layer)
+    pub fn with_layers(layers: Vec<Layer>) -> Self {
+        let active = layers.len().saturating_sub(1);
         let mut me = Self {
             base: ControlBase::with_focus_overlay(Layout::fill()),
-            surface: Surface::new(width, height),
+            history: History::new(&layers, active, history::DEFAULT_BUDGET),
+            active,
+            layers,
             scrollbars: ScrollBars::new(true),
-            selection: Selection::new(true),
+            selection: Selection::new(),
             drawwing_object: DrawingObject::Selection(SelectionObject::default()),
             is_move_dragging: false,
             move_drag_start: Point::new(0, 0),
             move_drag_initial_offset: Point::new(0, 0),
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            max_undo_levels: 50,
+            picking: false,
             clipboard: None,
+            paste_mode: PasteMode::Opaque,
+            picked: None,
         };
         me.set_components_toolbar_margins(3, 5);
-        me.scrollbars.resize(
-            me.surface.size().width as u64,
-            me.surface.size().height as u64,
-            &me.base,
-        );
-        me.save_state();
+        me.update_scrollbars();
         me
     }
-    pub fn from_path(path: &Path) -> Option<Self> {
-        if let Ok(surface) = Surface::from_file(path) {
-            let mut me = Self {
-                base: ControlBase::with_focus_overlay(Layout::fill()),
-                surface,
-                scrollbars: ScrollBars::new(true),
-                selection: Selection::new(true),
-                drawwing_object: DrawingObject::Selection(SelectionObject::default()),
-                is_move_dragging: false,
-                move_drag_start: Point::new(0, 0),
-                move_drag_initial_offset: Point::new(0, 0),
-                undo_stack: Vec::new(),
-                redo_stack: Vec::new(),
-                max_undo_levels: 50,
-                clipboard: None,
-            };
-            me.set_components_toolbar_margins(3, 5);
-            me.scrollbars.resize(
-                me.surface.size().width as u64,
-                me.surface.size().height as u64,
-                &me.base,
-            );
-            me.save_state();
-            Some(me)
-        } else {
-            None
-        }
+    pub fn canvas_size(&self) -> Size {
+        self.layers[0].cells.size()
+    }
+    fn update_scrollbars(&mut self) {
+        let size = self.canvas_size();
+        self.scrollbars
+            .resize(size.width as u64, size.height as u64, &self.base);
+    }
+    // all visible layers composed into a single surface
+    pub fn composite(&self) -> Surface {
+        layer::flatten(&self.layers)
+    }
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+    pub fn active_layer(&self) -> usize {
+        self.active
+    }
+    // hidden and locked layers can not be drawn on
+    fn is_editable(&self) -> bool {
+        let layer = &self.layers[self.active];
+        layer.visible && !layer.locked
+    }
+
+    // the character sampled by the last eyedropper (Alt+click) use
+    pub fn picked_character(&self) -> Option<Character> {
+        self.picked
     }
 
     pub fn clear_surface(&mut self) {
-        self.surface.clear(char!("' ',black,black"));
+        let size = self.canvas_size();
+        self.layers[self.active].cells =
+            Cells::new(size.width, size.height, char!("' ',black,black"));
+        self.record_edit(None, "Clear");
     }
 
     pub fn load_from_file(&mut self, file: &Path) -> Result<(), String> {
         if let Ok(surface) = Surface::from_file(file) {
-            self.surface = surface;
-            self.scrollbars.resize(
-                self.surface.size().width as u64,
-                self.surface.size().height as u64,
-                &self.base,
-            );
+            self.layers = vec![Layer::with_surface("Background", surface)];
+            self.active = 0;
+            self.history.clear(&self.layers, self.active);
+            self.update_scrollbars();
             Ok(())
         } else {
             Err(format!(
@@ -98,20 +178,33 @@ impl PainterControl {
     }
 
     pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
-        self.surface
+        self.composite()
             .save(path)
             .map_err(|e| format!("Failed to save surface to file '{}': {}", path.display(), e))
     }
     pub fn reset(&mut self, d: DrawingObject) {
-        let is_selecton = matches!(d, DrawingObject::Selection(_));
-        self.selection = Selection::new(!is_selecton);
+        self.selection = Selection::new();
         self.drawwing_object = d;
     }
-    pub fn update_rectangle_properties(&mut self, fore: Color, back: Color, line_type: LineType) {
+    pub fn update_selection_properties(&mut self, nine_slice: bool) {
+        if let DrawingObject::Selection(sel) = &mut self.drawwing_object {
+            sel.nine_slice = nine_slice;
+        }
+    }
+    pub fn update_rectangle_properties(
+        &mut self,
+        fore: Color,
+        back: Color,
+        flags: CharFlags,
+        line_type: LineType,
+        auto_join: bool,
+    ) {
         if let DrawingObject::Rectangle(ref mut rect) = self.drawwing_object {
             rect.fore = fore;
             rect.back = back;
+            rect.flags = flags;
             rect.line_type = line_type;
+            rect.auto_join = auto_join;
         }
     }
     pub fn update_fillrectangle_properties(
@@ -132,14 +225,20 @@ impl PainterControl {
         &mut self,
         fore: Color,
         back: Color,
+        flags: CharFlags,
         line_type: LineType,
-        vertical: bool,
+        direction: LineDirection,
+        fixed_char: Option<char>,
+        auto_join: bool,
     ) {
         if let DrawingObject::Line(ref mut line) = self.drawwing_object {
             line.fore = fore;
             line.back = back;
+            line.flags = flags;
             line.line_type = line_type;
-            line.vertical = vertical;
+            line.direction = direction;
+            line.fixed_char = fixed_char;
+            line.auto_join = auto_join;
         }
     }
     pub fn update_text_properties(
@@ -156,132 +255,427 @@ impl PainterControl {
             text.flags = flags;
         }
     }
+    pub fn update_pencil_properties(
+        &mut self,
+        fore: Color,
+        back: Color,
+        ch: char,
+        flags: CharFlags,
+    ) {
+        if let DrawingObject::Pencil(ref mut pencil) = self.drawwing_object {
+            pencil.fore = fore;
+            pencil.back = back;
+            pencil.ch = ch;
+            pencil.flags = flags;
+        }
+    }
+    pub fn update_eraser_properties(&mut self, width: u32, height: u32, blank: Character) {
+        if let DrawingObject::Eraser(ref mut eraser) = self.drawwing_object {
+            eraser.width = width;
+            eraser.height = height;
+            eraser.blank = blank;
+        }
+    }
+    pub fn update_floodfill_properties(
+        &mut self,
+        fore: Color,
+        back: Color,
+        ch: char,
+        flags: CharFlags,
+        match_mode: FillMatch,
+        diagonal: bool,
+    ) {
+        if let DrawingObject::FloodFill(ref mut fill) = self.drawwing_object {
+            fill.fore = fore;
+            fill.back = back;
+            fill.ch = ch;
+            fill.flags = flags;
+            fill.match_mode = match_mode;
+            fill.diagonal = diagonal;
+        }
+    }
+    pub fn update_ellipse_properties(
+        &mut self,
+        fore: Color,
+        back: Color,
+        ch: char,
+        flags: CharFlags,
+        filled: bool,
+        aspect_correction: bool,
+    ) {
+        if let DrawingObject::Ellipse(ref mut ellipse) = self.drawwing_object {
+            ellipse.fore = fore;
+            ellipse.back = back;
+            ellipse.ch = ch;
+            ellipse.flags = flags;
+            ellipse.filled = filled;
+            ellipse.aspect_correction = aspect_correction;
+        }
+    }
+    pub fn update_connector_properties(
+        &mut self,
+        fore: Color,
+        back: Color,
+        flags: CharFlags,
+        line_type: LineType,
+        start_arrow: ArrowHead,
+        end_arrow: ArrowHead,
+        rounded: bool,
+    ) {
+        if let DrawingObject::Connector(ref mut connector) = self.drawwing_object {
+            connector.fore = fore;
+            connector.back = back;
+            connector.flags = flags;
+            connector.line_type = line_type;
+            connector.start_arrow = start_arrow;
+            connector.end_arrow = end_arrow;
+            connector.rounded = rounded;
+        }
+    }
     pub fn write_current_object(&mut self) {
-        if self.selection.is_visible() {
-            self.save_state();
-
-            self.drawwing_object
-                .paint(&mut self.surface, self.selection.rect());
+        if self.drawwing_object.is_path() {
+            self.finish_path();
+            return;
+        }
+        if self.selection.is_visible() && self.is_editable() {
+            let rect = self.selection.rect();
+            let mut area = self.drawwing_object.touched_area(rect, self.canvas_size());
+            let before = if self.drawwing_object.auto_join() {
+                // the joined glyphs depend on the cells around the rectangle
+                area.inflate_width(1, 1, 1, 1);
+                Some(self.copy_region(rect))
+            } else {
+                None
+            };
+            let origin = self.selection.origin();
+            let bottom_layer = self.active == 0;
+            let cells = &mut self.layers[self.active].cells;
+            match &self.drawwing_object {
+                DrawingObject::Selection(sel) => sel.place(cells, rect, bottom_layer),
+                object => cells.draw(area, |surface| {
+                    object.paint(surface, rect, origin);
+                    if let Some(before) = before {
+                        box_join::join(surface, &before, rect);
+                    }
+                }),
+            }
+            let name = format!(
+                "{} {}x{}",
+                self.drawwing_object.name(),
+                rect.width(),
+                rect.height()
+            );
+            self.record_edit(Some(area), &name);
             self.drawwing_object.clear();
             self.selection.reset();
         }
     }
+    // flips / rotates the floating selection (a rotated selection swaps its width and height)
+    pub fn transform_selection(&mut self, transform: Transform) -> bool {
+        if !self.selection.is_visible() || !self.drawwing_object.transform_selection(transform) {
+            return false;
+        }
+        let rect = self.selection.rect();
+        self.selection
+            .set_size(transform.size(Size::new(rect.width(), rect.height())));
+        true
+    }
     pub fn cancel_selection(&mut self) {
-        if self.selection.is_visible() {
+        if self.selection.is_visible() || self.drawwing_object.has_path() {
             self.drawwing_object.clear();
             self.selection.reset();
         }
     }
 
-    pub fn save_state(&mut self) {
-        let size = self.surface.size();
-        let mut surface_copy = Surface::new(size.width, size.height);
-
-        for y in 0..size.height as i32 {
-            for x in 0..size.width as i32 {
-                if let Some(ch) = self.surface.char(x, y) {
-                    surface_copy.write_char(x, y, *ch);
-                }
-            }
+    // adds the cells changed on the active layer (since the previous step) as an undo step; `area` limits
+    // the search for changed cells when the edited part is known
+    fn record_edit(&mut self, area: Option<Rect>, name: &str) {
+        if self
+            .history
+            .record_cells(name, &self.layers, self.active, area)
+        {
+            self.raise_event(paintercontrol::Events::HistoryChanged);
         }
+    }
 
-        self.undo_stack.push(surface_copy);
-        self.redo_stack.clear();
+    fn record(&mut self, changes: Vec<Change>, active_before: usize, name: &str) {
+        self.history
+            .record(name, changes, (active_before, self.active), &self.layers);
+        self.raise_event(paintercontrol::Events::HistoryChanged);
+    }
 
-        if self.undo_stack.len() > self.max_undo_levels {
-            self.undo_stack.remove(0);
-        }
+    // called after the history moved to another step (`active` is the active layer of that step)
+    fn restored(&mut self, active: Option<usize>) -> bool {
+        let Some(active) = active else {
+            return false;
+        };
+        self.active = active;
+        self.update_scrollbars();
+        self.selection.reset();
+        self.drawwing_object.clear();
+        // the layer stack (names, order, visibility) may have changed as well
+        self.raise_event(paintercontrol::Events::HistoryChanged);
+        true
     }
 
     pub fn undo(&mut self) -> bool {
-        if let Some(previous_surface) = self.undo_stack.pop() {
-            let size = self.surface.size();
-            let mut current_surface_copy = Surface::new(size.width, size.height);
-
-            for y in 0..size.height as i32 {
-                for x in 0..size.width as i32 {
-                    if let Some(ch) = self.surface.char(x, y) {
-                        current_surface_copy.write_char(x, y, *ch);
-                    }
-                }
-            }
+        let active = self.history.undo(&mut self.layers);
+        self.restored(active)
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let active = self.history.redo(&mut self.layers);
+        self.restored(active)
+    }
 
-            self.redo_stack.push(current_surface_copy);
+    // undoes / redoes steps until `position` steps (see `history()`) are applied
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        let active = self.history.jump(&mut self.layers, position);
+        self.restored(active)
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear(&self.layers, self.active);
+        self.raise_event(paintercontrol::Events::HistoryChanged);
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    // `rect` becomes the new canvas (it is given in the coordinates of the current one). The new area is
+    // filled with `fill` on the bottom layer and left transparent on the others.
+    pub fn set_canvas(&mut self, rect: Rect, fill: Character, name: &str) {
+        let before = self.layers.clone();
+        layer::crop_layers(&mut self.layers, rect, fill);
+        self.record(
+            vec![Change::Canvas { before, rect, fill }],
+            self.active,
+            name,
+        );
+        self.selection.reset();
+        self.drawwing_object.clear();
+        self.update_scrollbars();
+    }
+
+    // the empty / transparent cells of the bottom layer take the code and the colors of `fill` (this is not
+    // recorded in the history)
+    pub fn fill_background(&mut self, fill: Character) {
+        layer::fill_transparent(&mut self.layers[0].cells, fill);
+        self.history.sync(&self.layers, self.active);
+    }
+
+    pub fn crop_to_selection(&mut self) -> bool {
+        if !self.selection.is_visible()
+            || !matches!(self.drawwing_object, DrawingObject::Selection(_))
+        {
+            return false;
+        }
+        let rect = self.selection.rect();
+        self.set_canvas(rect, char!("' ',black,black"), "Crop to selection");
+        true
+    }
 
-            if self.redo_stack.len() > self.max_undo_levels {
-                self.redo_stack.remove(0);
+    // crops the canvas to the smallest rectangle that contains the (visible) painting
+    pub fn trim_to_content(&mut self) -> bool {
+        let size = self.canvas_size();
+        match canvas_size::content_bounds(&self.composite()) {
+            Some(rect) if rect.width() != size.width || rect.height() != size.height => {
+                self.set_canvas(rect, layer::EMPTY, "Trim to content");
+                true
             }
+            _ => false,
+        }
+    }
 
-            self.surface = previous_surface;
+    pub fn set_active_layer(&mut self, index: usize) {
+        if index < self.layers.len() && index != self.active {
+            self.active = index;
+            self.history.sync(&self.layers, self.active);
             self.selection.reset();
             self.drawwing_object.clear();
-            true
-        } else {
-            false
         }
     }
 
-    pub fn redo(&mut self) -> bool {
-        if let Some(next_surface) = self.redo_stack.pop() {
-            let size = self.surface.size();
-            let mut current_surface_copy = Surface::new(size.width, size.height);
-
-            for y in 0..size.height as i32 {
-                for x in 0..size.width as i32 {
-                    if let Some(ch) = self.surface.char(x, y) {
-                        current_surface_copy.write_char(x, y, *ch);
-                    }
-                }
-            }
+    // adds an empty layer above the active one
+    pub fn add_layer(&mut self, name: &str) {
+        let size = self.canvas_size();
+        let layer = Layer::new(name, size.width, size.height);
+        self.insert_layer(layer, "Add layer");
+    }
 
-            self.undo_stack.push(current_surface_copy);
+    fn insert_layer(&mut self, layer: Layer, name: &str) {
+        let active_before = self.active;
+        self.active += 1;
+        self.layers.insert(self.active, layer.clone());
+        self.record(
+            vec![Change::Insert {
+                index: self.active,
+                layer,
+            }],
+            active_before,
+            name,
+        );
+    }
 
-            if self.undo_stack.len() > self.max_undo_levels {
-                self.undo_stack.remove(0);
-            }
+    pub fn delete_layer(&mut self) -> bool {
+        if self.layers.len() < 2 {
+            return false;
+        }
+        let active_before = self.active;
+        let layer = self.layers.remove(self.active);
+        self.active = self.active.min(self.layers.len() - 1);
+        self.record(
+            vec![Change::Remove {
+                index: active_before,
+                layer,
+            }],
+            active_before,
+            "Delete layer",
+        );
+        true
+    }
 
-            self.surface = next_surface;
-            self.selection.reset();
-            self.drawwing_object.clear();
-            true
-        } else {
-            false
+    pub fn duplicate_layer(&mut self) {
+        let mut copy = self.layers[self.active].clone();
+        copy.name.push_str(" copy");
+        self.insert_layer(copy, "Duplicate layer");
+    }
+
+    pub fn move_layer_up(&mut self) -> bool {
+        if self.active + 1 >= self.layers.len() {
+            return false;
         }
+        self.layers.swap(self.active, self.active + 1);
+        self.active += 1;
+        self.record(
+            vec![Change::Swap {
+                index: self.active - 1,
+            }],
+            self.active - 1,
+            "Move layer up",
+        );
+        true
     }
 
-    pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+    pub fn move_layer_down(&mut self) -> bool {
+        if self.active == 0 {
+            return false;
+        }
+        self.layers.swap(self.active, self.active - 1);
+        self.active -= 1;
+        self.record(
+            vec![Change::Swap { index: self.active }],
+            self.active + 1,
+            "Move layer down",
+        );
+        true
     }
 
-    pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
+    // merges the active layer into the one below it
+    pub fn merge_down(&mut self) -> bool {
+        if self.active == 0 || self.layers[self.active - 1].locked {
+            return false;
+        }
+        let active_before = self.active;
+        let upper = self.layers.remove(self.active);
+        self.active -= 1;
+        let lower = &mut self.layers[self.active];
+        let merged = layer::merge(lower, &upper);
+        let area = Rect::with_point_and_size(Point::new(0, 0), merged.size());
+        let cells = history::diff(self.active, &lower.cells, &merged, area);
+        lower.cells = merged;
+        let mut changes = vec![Change::Remove {
+            index: active_before,
+            layer: upper,
+        }];
+        changes.extend(cells);
+        self.record(changes, active_before, "Merge down");
+        true
+    }
+
+    // changes the name / visibility / opacity / lock state of a layer as a single undo step
+    fn update_layer(
+        &mut self,
+        index: usize,
+        name: &str,
+        update: impl FnOnce(&mut LayerProperties),
+    ) {
+        let Some(layer) = self.layers.get(index) else {
+            return;
+        };
+        let before = LayerProperties::of(layer);
+        let mut after = before.clone();
+        update(&mut after);
+        if after != before {
+            after.apply(&mut self.layers[index]);
+            self.record(
+                vec![Change::Properties {
+                    layer: index,
+                    before,
+                    after,
+                }],
+                self.active,
+                name,
+            );
+        }
+    }
+
+    pub fn rename_layer(&mut self, name: &str) {
+        self.update_layer(self.active, "Rename layer", |p| p.name = name.to_string());
+    }
+
+    pub fn set_layer_visible(&mut self, index: usize, visible: bool) {
+        let name = if visible { "Show layer" } else { "Hide layer" };
+        self.update_layer(index, name, |p| p.visible = visible);
+    }
+
+    pub fn set_layer_locked(&mut self, locked: bool) {
+        let name = if locked { "Lock layer" } else { "Unlock layer" };
+        self.update_layer(self.active, name, |p| p.locked = locked);
+    }
+
+    pub fn set_layer_opacity(&mut self, opacity: u8) {
+        let opacity = opacity.min(100);
+        let name = format!("Opacity {}%", opacity);
+        self.update_layer(self.active, &name, |p| p.opacity = opacity);
+    }
+
+    fn copy_region(&self, rect: Rect) -> Surface {
+        layer::crop_surface(&self.layers[self.active].cells, rect, layer::EMPTY)
     }
 
     pub fn copy_selection(&mut self) {
         if self.selection.is_visible()
             && matches!(self.drawwing_object, DrawingObject::Selection(_))
         {
-            let rect = self.selection.rect();
-            let mut clipboard_surface = Surface::new(rect.width(), rect.height());
+            // transparent cells stay transparent (they do not cover the painting when pasted)
+            self.clipboard = Some(self.copy_region(self.selection.rect()));
+        }
+    }
 
-            for y in 0..rect.height() as i32 {
-                for x in 0..rect.width() as i32 {
-                    if let Some(ch) = self.surface.char(rect.left() + x, rect.top() + y) {
-                        clipboard_surface.write_char(x, y, *ch);
-                    }
-                }
-            }
+    // the mode used by Ctrl+V
+    pub fn set_paste_mode(&mut self, mode: PasteMode) {
+        self.paste_mode = mode;
+    }
 
-            self.clipboard = Some(clipboard_surface);
-        }
+    pub fn paste_mode(&self) -> PasteMode {
+        self.paste_mode
     }
 
-    pub fn paste_from_clipboard(&mut self) {
-        if self.clipboard.is_none() || !self.selection.is_visible() {
+    pub fn paste_from_clipboard(&mut self, mode: PasteMode) {
+        if self.clipboard.is_none() || !self.selection.is_visible() || !self.is_editable() {
             return;
         }
 
-        self.save_state();
         let rect = self.selection.rect();
 
         // Get clipboard dimensions first
@@ -294,7 +688,16 @@ impl PainterControl {
             return;
         };
 
+        let background = match (mode, &self.clipboard) {
+            (PasteMode::SkipBackground, Some(clipboard_surface)) => {
+                background_of(clipboard_surface)
+            }
+            _ => (' ', Color::Black),
+        };
+
         // Paste the clipboard content at the selection position
+        let size = self.canvas_size();
+        let cells = &mut self.layers[self.active].cells;
         for y in 0..clipboard_height as i32 {
             for x in 0..clipboard_width as i32 {
                 let target_x = rect.left() + x;
@@ -303,23 +706,103 @@ impl PainterControl {
                 // Check bounds
                 if target_x >= 0
                     && target_y >= 0
-                    && target_x < self.surface.size().width as i32
-                    && target_y < self.surface.size().height as i32
+                    && target_x < size.width as i32
+                    && target_y < size.height as i32
                 {
                     if let Some(clipboard_surface) = &self.clipboard {
-                        if let Some(ch) = clipboard_surface.char(x, y) {
-                            self.surface.write_char(target_x, target_y, *ch);
+                        let target = cells.char(target_x, target_y);
+                        if let Some(ch) = clipboard_surface
+                            .char(x, y)
+                            .and_then(|ch| mode.cell(*ch, target, background))
+                        {
+                            cells.write_char(target_x, target_y, ch);
                         }
                     }
                 }
             }
         }
 
+        self.record_edit(
+            Some(Rect::with_point_and_size(
+                rect.top_left(),
+                Size::new(clipboard_width, clipboard_height),
+            )),
+            "Paste",
+        );
+
         // Clear the selection after pasting
         self.selection.reset();
         self.drawwing_object.clear();
     }
 
+    fn process_stroke_event(&mut self, event: &MouseEvent) -> EventProcessStatus {
+        if !self.is_editable() {
+            return EventProcessStatus::Processed;
+        }
+        match event {
+            MouseEvent::Pressed(data) => {
+                self.drawwing_object.stroke_to(
+                    &mut self.layers[self.active].cells,
+                    Point::new(data.x, data.y),
+                );
+                EventProcessStatus::Processed
+            }
+            MouseEvent::Drag(data) => {
+                self.drawwing_object.stroke_to(
+                    &mut self.layers[self.active].cells,
+                    Point::new(data.x, data.y),
+                );
+                EventProcessStatus::Processed
+            }
+            MouseEvent::Released(_) => {
+                // the whole stroke (since the mouse was pressed) is a single undo step
+                if let Some(area) = self.drawwing_object.end_stroke() {
+                    let name = self.drawwing_object.name();
+                    self.record_edit(Some(area), name);
+                }
+                EventProcessStatus::Processed
+            }
+            _ => EventProcessStatus::Ignored,
+        }
+    }
+
+    fn process_path_event(&mut self, event: &MouseEvent) -> EventProcessStatus {
+        match event {
+            MouseEvent::Pressed(data) => {
+                self.drawwing_object
+                    .add_path_point(Point::new(data.x, data.y));
+                EventProcessStatus::Processed
+            }
+            MouseEvent::Over(point) => {
+                if self.drawwing_object.has_path() {
+                    self.drawwing_object.set_path_cursor(*point);
+                    EventProcessStatus::Processed
+                } else {
+                    EventProcessStatus::Ignored
+                }
+            }
+            MouseEvent::DoubleClick(data) => {
+                self.drawwing_object
+                    .add_path_point(Point::new(data.x, data.y));
+                self.finish_path();
+                EventProcessStatus::Processed
+            }
+            _ => EventProcessStatus::Ignored,
+        }
+    }
+
+    fn finish_path(&mut self) {
+        if let (Some(area), true) = (self.drawwing_object.path_area(), self.is_editable()) {
+            let object = &self.drawwing_object;
+            self.layers[self.active]
+                .cells
+                .draw(area, |surface| object.paint_path(surface, false));
+            let name = self.drawwing_object.name();
+            self.record_edit(Some(area), name);
+        }
+        self.drawwing_object.clear();
+    }
+
     fn adjust_mouse_event_for_scroll(&self, event: &MouseEvent) -> MouseEvent {
         let offset = self.scrollbars.offset();
         match event {
@@ -341,6 +824,15 @@ impl PainterControl {
                 button: data.button,
                 modifier: data.modifier,
             }),
+            MouseEvent::DoubleClick(data) => MouseEvent::DoubleClick(MouseEventData {
+                x: data.x - offset.x,
+                y: data.y - offset.y,
+                button: data.button,
+                modifier: data.modifier,
+            }),
+            MouseEvent::Over(point) => {
+                MouseEvent::Over(Point::new(point.x - offset.x, point.y - offset.y))
+            }
             _ => *event,
         }
     }
@@ -353,10 +845,16 @@ impl OnPaint for PainterControl {
             surface.reduce_clip_by(0, 0, 1, 1);
         }
         let o = self.scrollbars.offset();
-        surface.draw_surface(o.x, o.y, &self.surface);
+        // only the visible part of the canvas is composed
+        let visible = Rect::with_point_and_size(Point::new(-o.x, -o.y), self.size());
+        layer::draw(&self.layers, surface, o.x, o.y, visible);
         surface.set_origin(o.x, o.y);
         if self.selection.is_visible() {
-            self.drawwing_object.paint(surface, self.selection.rect());
+            self.drawwing_object
+                .paint(surface, self.selection.rect(), self.selection.origin());
+        }
+        if self.drawwing_object.has_path() {
+            self.drawwing_object.paint_path(surface, true);
         }
         self.selection.paint(surface, theme);
     }
@@ -401,17 +899,59 @@ impl OnMouseEvent for PainterControl {
             return EventProcessStatus::Processed;
         }
 
+        let adjusted_event = self.adjust_mouse_event_for_scroll(event);
+        if let MouseEvent::Pressed(data) = adjusted_event {
+            if data.modifier.contains(KeyModifier::Alt) {
+                // eyedropper
+                self.picking = true;
+                if let Some(ch) = layer::cell(&self.layers, data.x, data.y) {
+                    self.picked = Some(ch);
+                    self.raise_event(paintercontrol::Events::CharacterPicked);
+                }
+                return EventProcessStatus::Processed;
+            }
+        }
+        if self.picking {
+            match adjusted_event {
+                MouseEvent::Drag(_) => return EventProcessStatus::Processed,
+                MouseEvent::Released(_) => {
+                    self.picking = false;
+                    return EventProcessStatus::Processed;
+                }
+                _ => {}
+            }
+        }
+
         if is_move_mode {
             return EventProcessStatus::Processed;
         }
 
-        let adjusted_event = self.adjust_mouse_event_for_scroll(event);
+        if self.drawwing_object.is_stroke() {
+            return self.process_stroke_event(&adjusted_event);
+        }
+        if self.drawwing_object.is_path() {
+            return self.process_path_event(&adjusted_event);
+        }
+        if self.drawwing_object.is_click() {
+            if let MouseEvent::Pressed(data) = adjusted_event {
+                if self.is_editable() {
+                    if let Some(area) = self.drawwing_object.click(
+                        &mut self.layers[self.active].cells,
+                        Point::new(data.x, data.y),
+                    ) {
+                        let name = self.drawwing_object.name();
+                        self.record_edit(Some(area), name);
+                    }
+                }
+            }
+            return EventProcessStatus::Processed;
+        }
         let during_creation = self.selection.is_during_creation();
         if self.selection.process_mouse_event(&adjusted_event) {
             if during_creation && self.selection.is_visible() {
                 // tocmai am creat o selectie noua
                 self.drawwing_object
-                    .on_finish_selection(&self.surface, self.selection.rect());
+                    .on_finish_selection(&self.layers[self.active].cells, self.selection.rect());
             }
             return EventProcessStatus::Processed;
         }
@@ -427,6 +967,20 @@ impl OnMouseEvent for PainterControl {
 
 impl OnKeyPressed for PainterControl {
     fn on_key_pressed(&mut self, key: Key, _character: char) -> EventProcessStatus {
+        let transform = match key.value() {
+            key!("H") => Some(Transform::FlipHorizontal),
+            key!("V") => Some(Transform::FlipVertical),
+            key!("R") => Some(Transform::RotateClockwise),
+            key!("Shift+R") => Some(Transform::RotateCounterClockwise),
+            _ => None,
+        };
+        if let Some(transform) = transform {
+            return if self.transform_selection(transform) {
+                EventProcessStatus::Processed
+            } else {
+                EventProcessStatus::Ignored
+            };
+        }
         match key.value() {
             key!("Up") => {
                 self.scrollbars.set_indexes(
@@ -495,9 +1049,15 @@ impl OnKeyPressed for PainterControl {
                     EventProcessStatus::Ignored
                 }
             }
-            key!("Ctrl+V") => {
+            key!("Ctrl+V") | key!("Ctrl+Shift+V") => {
+                // Shift pastes transparently, whatever the selected mode is
+                let mode = if key.modifier.contains(KeyModifier::Shift) {
+                    PasteMode::SkipSpaces
+                } else {
+                    self.paste_mode
+                };
                 if self.clipboard.is_some() && self.selection.is_visible() {
-                    self.paste_from_clipboard();
+                    self.paste_from_clipboard(mode);
                     EventProcessStatus::Processed
                 } else {
                     EventProcessStatus::Ignored
@@ -510,10 +1070,6 @@ impl OnKeyPressed for PainterControl {
 
 impl OnResize for PainterControl {
     fn on_resize(&mut self, _old_size: Size, _new_size: Size) {
-        self.scrollbars.resize(
-            self.surface.size().width as u64,
-            self.surface.size().height as u64,
-            &self.base,
-        );
+        self.update_scrollbars();
     }
 }
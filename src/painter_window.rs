@@ -1,71 +1,280 @@
 use std::path::Path;
 
+use crate::canvas_size::CanvasSizeOptions;
+use crate::drawing_object::ArrowHead;
+use crate::drawing_object::ConnectorObject;
 use crate::drawing_object::DrawingObject;
+use crate::drawing_object::EllipseObject;
+use crate::drawing_object::EraserObject;
+use crate::drawing_object::FillMatch;
 use crate::drawing_object::FillRectangleObject;
+use crate::drawing_object::FloodFillObject;
+use crate::drawing_object::LineDirection;
 use crate::drawing_object::LineObject;
 use crate::drawing_object::MoveObject;
+use crate::drawing_object::PencilObject;
 use crate::drawing_object::RectangleObject;
 use crate::drawing_object::SelectionObject;
 use crate::drawing_object::TextObject;
+use crate::layer;
+use crate::palette_bar::PaletteBar;
+use crate::palette_bar::palettebar;
+use crate::project;
+use crate::project::DocumentInfo;
+use crate::project::Project;
+use crate::transform::Transform;
 use appcui::graphics::LineType;
 use appcui::prelude::*;
 
 use super::painter_control::PainterControl;
+use super::painter_control::PasteMode;
+use super::painter_control::paintercontrol;
 
-#[Window(events = MenuEvents + ColorPickerEvents + SelectorEvents<LineType> + ButtonEvents + AccordionEvents + CharPickerEvents + RadioBoxEvents,
-        commands = ForegroundColor + BackgroundColor + Char25 + Char50 + Char75 + Char100)]
+// names of the accordion panels (used as keys for the tool settings saved in a project)
+const TOOL_NAMES: [&str; 11] = [
+    "selection",
+    "move",
+    "rectangle",
+    "fill",
+    "line",
+    "text",
+    "pencil",
+    "eraser",
+    "floodfill",
+    "ellipse",
+    "connector",
+];
+
+// the drawing object of a tool panel (`None` for the panels that are not tools)
+fn drawing_object(panel: usize) -> Option<DrawingObject> {
+    match panel {
+        0 => Some(DrawingObject::Selection(SelectionObject::default())),
+        1 => Some(DrawingObject::Move(MoveObject::default())),
+        2 => Some(DrawingObject::Rectangle(RectangleObject::default())),
+        3 => Some(DrawingObject::FillRectangle(FillRectangleObject::default())),
+        4 => Some(DrawingObject::Line(LineObject::default())),
+        5 => Some(DrawingObject::Text(TextObject::default())),
+        6 => Some(DrawingObject::Pencil(PencilObject::default())),
+        7 => Some(DrawingObject::Eraser(EraserObject::default())),
+        8 => Some(DrawingObject::FloodFill(FloodFillObject::default())),
+        9 => Some(DrawingObject::Ellipse(EllipseObject::default())),
+        10 => Some(DrawingObject::Connector(ConnectorObject::default())),
+        _ => None,
+    }
+}
+
+// Bold / Italic / Underline check boxes of a tool panel
+#[derive(Copy, Clone)]
+struct FlagControls {
+    bold: Handle<CheckBox>,
+    italic: Handle<CheckBox>,
+    underline: Handle<CheckBox>,
+}
+impl FlagControls {
+    fn new() -> Self {
+        Self {
+            bold: Handle::None,
+            italic: Handle::None,
+            underline: Handle::None,
+        }
+    }
+}
+
+#[Window(events = MenuEvents + ColorPickerEvents + SelectorEvents<LineType> + SelectorEvents<FillMatch> + SelectorEvents<ArrowHead> + ButtonEvents + AccordionEvents + CharPickerEvents + RadioBoxEvents + CheckBoxEvents + NumericSelectorEvents<u32> + ListBoxEvents,
+        custom_events = PainterControlEvents + PaletteBarEvents,
+        commands = ForegroundColor + BackgroundColor + Char25 + Char50 + Char75 + Char100 + Paste + PasteOpaque + PasteSkipSpaces + PasteSkipBackground + PasteForegroundOnly)]
 pub struct PainterWindow {
     painter: Handle<PainterControl>,
     tmp_string: String,
     acc: Handle<Accordion>,
     menu: Handle<Menu>,
+    info: DocumentInfo,
+    palette: Handle<PaletteBar>,
+    // tool settings of an opened project (they can only be applied once the window is on the desktop)
+    pending_tool_settings: Vec<(String, String)>,
     // rectangle
     rectangle_fore: Handle<ColorPicker>,
     rectangle_back: Handle<ColorPicker>,
     rectangle_line_type: Handle<Selector<LineType>>,
+    rectangle_join: Handle<CheckBox>,
+    rectangle_flags: FlagControls,
     // fill rectangle
     fill_fore: Handle<ColorPicker>,
     fill_back: Handle<ColorPicker>,
     fill_char: Handle<CharPicker>,
+    fill_flags: FlagControls,
     // line
     line_fore: Handle<ColorPicker>,
     line_back: Handle<ColorPicker>,
     line_type: Handle<Selector<LineType>>,
     line_vert: Handle<RadioBox>,
+    line_horizontal: Handle<RadioBox>,
+    line_free: Handle<RadioBox>,
+    line_fixed: Handle<CheckBox>,
+    line_fixed_char: Handle<CharPicker>,
+    line_join: Handle<CheckBox>,
+    line_flags: FlagControls,
     // Text
     text_fore: Handle<ColorPicker>,
     text_back: Handle<ColorPicker>,
     text_content: Handle<TextArea>,
+    text_flags: FlagControls,
+    // pencil
+    pencil_fore: Handle<ColorPicker>,
+    pencil_back: Handle<ColorPicker>,
+    pencil_char: Handle<CharPicker>,
+    pencil_flags: FlagControls,
+    // eraser
+    eraser_width: Handle<NumericSelector<u32>>,
+    eraser_height: Handle<NumericSelector<u32>>,
+    eraser_char: Handle<CharPicker>,
+    eraser_fore: Handle<ColorPicker>,
+    eraser_back: Handle<ColorPicker>,
+    eraser_flags: FlagControls,
+    // flood fill
+    floodfill_fore: Handle<ColorPicker>,
+    floodfill_back: Handle<ColorPicker>,
+    floodfill_char: Handle<CharPicker>,
+    floodfill_match: Handle<Selector<FillMatch>>,
+    floodfill_diagonal: Handle<CheckBox>,
+    floodfill_flags: FlagControls,
+    // ellipse
+    ellipse_fore: Handle<ColorPicker>,
+    ellipse_back: Handle<ColorPicker>,
+    ellipse_char: Handle<CharPicker>,
+    ellipse_filled: Handle<CheckBox>,
+    ellipse_aspect: Handle<CheckBox>,
+    ellipse_flags: FlagControls,
+    // connector
+    connector_fore: Handle<ColorPicker>,
+    connector_back: Handle<ColorPicker>,
+    connector_line_type: Handle<Selector<LineType>>,
+    connector_start: Handle<Selector<ArrowHead>>,
+    connector_end: Handle<Selector<ArrowHead>>,
+    connector_rounded: Handle<CheckBox>,
+    connector_flags: FlagControls,
+    // selection
+    selection_transforms: Vec<(Handle<Button>, Transform)>,
+    selection_nine_slice: Handle<CheckBox>,
+    // layers
+    layers_list: Handle<ListBox>,
+    layer_name: Handle<TextField>,
+    layer_rename: Handle<Button>,
+    layer_locked: Handle<CheckBox>,
+    layer_opacity: Handle<NumericSelector<u32>>,
+    layer_add: Handle<Button>,
+    layer_duplicate: Handle<Button>,
+    layer_delete: Handle<Button>,
+    layer_up: Handle<Button>,
+    layer_down: Handle<Button>,
+    layer_merge: Handle<Button>,
+    // history
+    history_list: Handle<ListBox>,
+    history_memory: Handle<Label>,
 }
 
 impl PainterWindow {
-    fn inner_new(name: &str, path: Option<&Path>) -> Result<Self, String> {
+    fn inner_new(name: &str, p: PainterControl) -> Result<Self, String> {
         let mut w = Self {
             base: Window::new(name, layout!("a:c,w:60,h:20"), window::Flags::Sizeable),
             tmp_string: String::with_capacity(1024),
             painter: Handle::None,
             acc: Handle::None,
             menu: Handle::None,
+            info: DocumentInfo::new(name),
+            palette: Handle::None,
+            pending_tool_settings: Vec::new(),
             rectangle_fore: Handle::None,
             rectangle_back: Handle::None,
             rectangle_line_type: Handle::None,
+            rectangle_join: Handle::None,
+            rectangle_flags: FlagControls::new(),
             fill_fore: Handle::None,
             fill_back: Handle::None,
             fill_char: Handle::None,
+            fill_flags: FlagControls::new(),
             line_fore: Handle::None,
             line_back: Handle::None,
             line_type: Handle::None,
             line_vert: Handle::None,
+            line_horizontal: Handle::None,
+            line_free: Handle::None,
+            line_fixed: Handle::None,
+            line_fixed_char: Handle::None,
+            line_join: Handle::None,
+            line_flags: FlagControls::new(),
             text_fore: Handle::None,
             text_back: Handle::None,
             text_content: Handle::None,
+            text_flags: FlagControls::new(),
+            pencil_fore: Handle::None,
+            pencil_back: Handle::None,
+            pencil_char: Handle::None,
+            pencil_flags: FlagControls::new(),
+            eraser_width: Handle::None,
+            eraser_height: Handle::None,
+            eraser_char: Handle::None,
+            eraser_fore: Handle::None,
+            eraser_back: Handle::None,
+            eraser_flags: FlagControls::new(),
+            floodfill_fore: Handle::None,
+            floodfill_back: Handle::None,
+            floodfill_char: Handle::None,
+            floodfill_match: Handle::None,
+            floodfill_diagonal: Handle::None,
+            floodfill_flags: FlagControls::new(),
+            ellipse_fore: Handle::None,
+            ellipse_back: Handle::None,
+            ellipse_char: Handle::None,
+            ellipse_filled: Handle::None,
+            ellipse_aspect: Handle::None,
+            ellipse_flags: FlagControls::new(),
+            connector_fore: Handle::None,
+            connector_back: Handle::None,
+            connector_line_type: Handle::None,
+            connector_start: Handle::None,
+            connector_end: Handle::None,
+            connector_rounded: Handle::None,
+            connector_flags: FlagControls::new(),
+            selection_transforms: Vec::new(),
+            selection_nine_slice: Handle::None,
+            layers_list: Handle::None,
+            layer_name: Handle::None,
+            layer_rename: Handle::None,
+            layer_locked: Handle::None,
+            layer_opacity: Handle::None,
+            layer_add: Handle::None,
+            layer_duplicate: Handle::None,
+            layer_delete: Handle::None,
+            layer_up: Handle::None,
+            layer_down: Handle::None,
+            layer_merge: Handle::None,
+            history_list: Handle::None,
+            history_memory: Handle::None,
         };
 
-        let mut vs = vsplitter!("pos: 90%,d:f");
+        let mut vs = vsplitter!("pos: 90%,l:0,t:0,r:0,b:1");
         let mut acc = accordion!("d:f");
 
         // Selection panel
         let id = acc.add_panel("Selection");
+        let flip_h = acc.add(id, button!("'Flip horizontal',l:1,t:1,r:1,h:1,type: Flat"));
+        let flip_v = acc.add(id, button!("'Flip vertical',l:1,t:2,r:1,h:1,type: Flat"));
+        let rotate_r = acc.add(id, button!("'Rotate right',l:1,t:3,r:1,h:1,type: Flat"));
+        let rotate_l = acc.add(id, button!("'Rotate left',l:1,t:4,r:1,h:1,type: Flat"));
+        let rotate_180 = acc.add(id, button!("'Rotate 180°',l:1,t:5,r:1,h:1,type: Flat"));
+        acc.add(id, label!("'Keys: H, V, R, Shift+R',l:1,t:7,r:1,h:1"));
+        w.selection_nine_slice = acc.add(
+            id,
+            checkbox!("'Keep borders (9-slice)',l:1,t:9,r:1,h:1,checked:false"),
+        );
+        w.selection_transforms = vec![
+            (flip_h, Transform::FlipHorizontal),
+            (flip_v, Transform::FlipVertical),
+            (rotate_r, Transform::RotateClockwise),
+            (rotate_l, Transform::RotateCounterClockwise),
+            (rotate_180, Transform::Rotate180),
+        ];
 
         // Move panel
         let id = acc.add_panel("Move");
@@ -78,6 +287,12 @@ impl PainterWindow {
         w.rectangle_fore = acc.add(id, colorpicker!("White,l:7,t:3,r:1"));
         acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
         w.rectangle_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
+        w.rectangle_join = acc.add(id, checkbox!("Auto-join,l:1,t:7,r:1,h:1,checked:true"));
+        w.rectangle_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:9,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:10,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:11,r:1,h:1,checked:false")),
+        };
 
         // Filled rectangle panel
         let id = acc.add_panel("Filled Rectangle");
@@ -87,6 +302,11 @@ impl PainterWindow {
         w.fill_fore = acc.add(id, colorpicker!("White,l:7,t:3,r:1"));
         acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
         w.fill_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
+        w.fill_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:7,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:8,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:9,r:1,h:1,checked:false")),
+        };
 
         // Line panel
         let id = acc.add_panel("Line");
@@ -97,7 +317,16 @@ impl PainterWindow {
         acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
         w.line_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
         w.line_vert = acc.add(id, radiobox!("Vertical,l:1,t:7,r:1,h:1,selected:true"));
-        acc.add(id, radiobox!("Horizontal,l:1,t:8,r:1,h:1,selected:false"));
+        w.line_horizontal = acc.add(id, radiobox!("Horizontal,l:1,t:8,r:1,h:1,selected:false"));
+        w.line_free = acc.add(id, radiobox!("'Any angle',l:1,t:9,r:1,h:1,selected:false"));
+        w.line_fixed = acc.add(id, checkbox!("'Fixed:',l:1,t:11,w:10,h:1,checked:false"));
+        w.line_fixed_char = acc.add(id, charpicker!("*,l:12,t:11,r:1,sets:[*]"));
+        w.line_join = acc.add(id, checkbox!("Auto-join,l:1,t:13,r:1,h:1,checked:true"));
+        w.line_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:15,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:16,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:17,r:1,h:1,checked:false")),
+        };
 
         // Text panel
         let id = acc.add_panel("Text");
@@ -105,24 +334,185 @@ impl PainterWindow {
         w.text_fore = acc.add(id, colorpicker!("White,l:7,t:1,r:1"));
         acc.add(id, label!("'Back:',x:1,y:3,w:5,h:1"));
         w.text_back = acc.add(id, colorpicker!("Black,l:7,t:3,r:1"));
+        w.text_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:5,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:6,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:7,r:1,h:1,checked:false")),
+        };
         w.text_content = acc.add(
             id,
-            textarea!("'Hello',l:1,t:5,r:1,b:0,flags:ShowLineNumber"),
+            textarea!("'Hello',l:1,t:9,r:1,b:0,flags:ShowLineNumber"),
         );
         //w.text_content = acc.add(id, textfield!("'',l:1,t:5,r:1,b:0,flags:ProcessEnter"));
 
-        let p = if let Some(path) = path {
-            if let Some(p) = PainterControl::from_path(path) {
-                p
-            } else {
-                PainterControl::new(100, 100)
-            }
-        } else {
-            PainterControl::new(100, 100)
+        // Pencil panel
+        let id = acc.add_panel("Pencil");
+        acc.add(id, label!("'Char:',x:1,y:1,w:5,h:1"));
+        w.pencil_char = acc.add(id, charpicker!("*,l:7,t:1,r:1,sets:[*]"));
+        acc.add(id, label!("'Fore:',x:1,y:3,w:5,h:1"));
+        w.pencil_fore = acc.add(id, colorpicker!("White,l:7,t:3,r:1"));
+        acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
+        w.pencil_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
+        w.pencil_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:7,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:8,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:9,r:1,h:1,checked:false")),
+        };
+
+        // Eraser panel
+        let id = acc.add_panel("Eraser");
+        acc.add(id, label!("'Width:',x:1,y:1,w:7,h:1"));
+        w.eraser_width = acc.add(
+            id,
+            numericselector!("u32,value:1,min:1,max:50,step:1,l:9,t:1,r:1"),
+        );
+        acc.add(id, label!("'Height:',x:1,y:3,w:7,h:1"));
+        w.eraser_height = acc.add(
+            id,
+            numericselector!("u32,value:1,min:1,max:50,step:1,l:9,t:3,r:1"),
+        );
+        acc.add(id, label!("'Char:',x:1,y:5,w:5,h:1"));
+        w.eraser_char = acc.add(id, charpicker!("code:32,l:7,t:5,r:1,sets:[*]"));
+        acc.add(id, label!("'Fore:',x:1,y:7,w:5,h:1"));
+        w.eraser_fore = acc.add(id, colorpicker!("White,l:7,t:7,r:1"));
+        acc.add(id, label!("'Back:',x:1,y:9,w:5,h:1"));
+        w.eraser_back = acc.add(id, colorpicker!("Black,l:7,t:9,r:1"));
+        w.eraser_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:11,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:12,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:13,r:1,h:1,checked:false")),
+        };
+
+        // Flood fill panel
+        let id = acc.add_panel("Flood Fill");
+        acc.add(id, label!("'Char:',x:1,y:1,w:5,h:1"));
+        w.floodfill_char = acc.add(id, charpicker!("*,l:7,t:1,r:1,sets:[*]"));
+        acc.add(id, label!("'Fore:',x:1,y:3,w:5,h:1"));
+        w.floodfill_fore = acc.add(id, colorpicker!("White,l:7,t:3,r:1"));
+        acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
+        w.floodfill_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
+        acc.add(id, label!("'Match:',x:1,y:7,w:6,h:1"));
+        w.floodfill_match = acc.add(id, selector!("FillMatch,l:8,t:7,r:1,value:Exact"));
+        w.floodfill_diagonal = acc.add(
+            id,
+            checkbox!("'8-way (diagonals)',l:1,t:9,r:1,h:1,checked:false"),
+        );
+        w.floodfill_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:11,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:12,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:13,r:1,h:1,checked:false")),
+        };
+
+        // Ellipse panel
+        let id = acc.add_panel("Ellipse");
+        acc.add(id, label!("'Char:',x:1,y:1,w:5,h:1"));
+        w.ellipse_char = acc.add(id, charpicker!("*,l:7,t:1,r:1,sets:[*]"));
+        acc.add(id, label!("'Fore:',x:1,y:3,w:5,h:1"));
+        w.ellipse_fore = acc.add(id, colorpicker!("White,l:7,t:3,r:1"));
+        acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
+        w.ellipse_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
+        w.ellipse_filled = acc.add(id, checkbox!("Filled,l:1,t:7,r:1,h:1,checked:false"));
+        w.ellipse_aspect = acc.add(
+            id,
+            checkbox!("'Aspect correction (circle)',l:1,t:8,r:1,h:1,checked:false"),
+        );
+        w.ellipse_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:10,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:11,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:12,r:1,h:1,checked:false")),
+        };
+
+        // Connector panel
+        let id = acc.add_panel("Connector");
+        acc.add(id, label!("'Type:',x:1,y:1,w:5,h:1"));
+        w.connector_line_type = acc.add(id, selector!("LineType,l:7,t:1,r:1,value:Single"));
+        acc.add(id, label!("'Fore:',x:1,y:3,w:5,h:1"));
+        w.connector_fore = acc.add(id, colorpicker!("White,l:7,t:3,r:1"));
+        acc.add(id, label!("'Back:',x:1,y:5,w:5,h:1"));
+        w.connector_back = acc.add(id, colorpicker!("Black,l:7,t:5,r:1"));
+        acc.add(id, label!("'Start:',x:1,y:7,w:6,h:1"));
+        w.connector_start = acc.add(id, selector!("ArrowHead,l:8,t:7,r:1,value:None"));
+        acc.add(id, label!("'End:',x:1,y:9,w:6,h:1"));
+        w.connector_end = acc.add(id, selector!("ArrowHead,l:8,t:9,r:1,value:Triangle"));
+        w.connector_rounded = acc.add(
+            id,
+            checkbox!("'Rounded corners',l:1,t:11,r:1,h:1,checked:false"),
+        );
+        w.connector_flags = FlagControls {
+            bold: acc.add(id, checkbox!("Bold,l:1,t:13,r:1,h:1,checked:false")),
+            italic: acc.add(id, checkbox!("Italic,l:1,t:14,r:1,h:1,checked:false")),
+            underline: acc.add(id, checkbox!("Underline,l:1,t:15,r:1,h:1,checked:false")),
         };
+
+        // Layers panel (the top layer is the first item, checked items are visible)
+        let id = acc.add_panel("Layers");
+        w.layers_list = acc.add(id, listbox!("l:1,t:1,r:1,h:6,flags: ScrollBars+CheckBoxes"));
+        acc.add(id, label!("'Name:',x:1,y:8,w:5,h:1"));
+        w.layer_name = acc.add(
+            id,
+            TextField::new("", layout!("l:7,t:8,r:1,h:1"), textfield::Flags::None),
+        );
+        w.layer_rename = acc.add(id, button!("&Rename,l:7,t:9,r:1,h:1,type: Flat"));
+        w.layer_locked = acc.add(id, checkbox!("Locked,l:1,t:11,r:1,h:1,checked:false"));
+        acc.add(id, label!("'Opacity:',x:1,y:13,w:8,h:1"));
+        w.layer_opacity = acc.add(
+            id,
+            numericselector!("u32,value:100,min:0,max:100,step:10,l:10,t:13,r:1"),
+        );
+        w.layer_add = acc.add(id, button!("&Add,l:1,t:15,r:1,h:1,type: Flat"));
+        w.layer_duplicate = acc.add(id, button!("D&uplicate,l:1,t:16,r:1,h:1,type: Flat"));
+        w.layer_delete = acc.add(id, button!("&Delete,l:1,t:17,r:1,h:1,type: Flat"));
+        w.layer_up = acc.add(id, button!("'Move up',l:1,t:18,r:1,h:1,type: Flat"));
+        w.layer_down = acc.add(id, button!("'Move down',l:1,t:19,r:1,h:1,type: Flat"));
+        w.layer_merge = acc.add(id, button!("'&Merge down',l:1,t:20,r:1,h:1,type: Flat"));
+
+        // History panel (the first item is the painting before the oldest step, the current item is the
+        // last applied step)
+        let id = acc.add_panel("History");
+        w.history_list = acc.add(id, listbox!("l:1,t:1,r:1,b:3,flags: ScrollBars"));
+        w.history_memory = acc.add(id, label!("'',l:1,b:1,r:1,h:1"));
+
         w.painter = vs.add(vsplitter::Panel::Left, p);
         w.acc = vs.add(vsplitter::Panel::Right, acc);
         w.add(vs);
+        w.palette = w.add(PaletteBar::new(&w.info.palette, layout!("l:0,b:0,r:0,h:1")));
+        w.refresh_layers();
+        w.refresh_history();
+
+        // the paste mode is used by Ctrl+V (Ctrl+Shift+V always skips spaces)
+        let mut m = Menu::new("&Edit");
+        m.add(menu::Command::new(
+            "&Paste",
+            Key::None,
+            painterwindow::Commands::Paste,
+        ));
+        m.add(menu::Separator::new());
+        for (caption, command, selected) in [
+            ("&Opaque paste", painterwindow::Commands::PasteOpaque, true),
+            (
+                "Paste skipping &spaces",
+                painterwindow::Commands::PasteSkipSpaces,
+                false,
+            ),
+            (
+                "Paste skipping &background",
+                painterwindow::Commands::PasteSkipBackground,
+                false,
+            ),
+            (
+                "Paste only &foreground",
+                painterwindow::Commands::PasteForegroundOnly,
+                false,
+            ),
+        ] {
+            m.add(menu::SingleChoice::new(
+                caption,
+                Key::None,
+                command,
+                selected,
+            ));
+        }
+        w.menu = w.register_menu(m);
 
         // let m = menu!("
         //     &Options,class:PainterWindow,items:[
@@ -151,21 +541,117 @@ impl PainterWindow {
         Ok(w)
     }
 
-    pub fn new(name: &str) -> Self {
-        Self::inner_new(name, None).unwrap()
+    pub fn new(name: &str, width: u32, height: u32, background: Character) -> Self {
+        Self::inner_new(name, PainterControl::new(width, height, background)).unwrap()
+    }
+
+    pub fn from_surface(name: &str, surface: Surface) -> Self {
+        Self::inner_new(name, PainterControl::with_surface(surface)).unwrap()
     }
 
+    // opens a project (.apj) or a flat surface (.srf)
     pub fn from_file(file: &Path) -> Result<Self, String> {
-        Self::inner_new("Painter", Some(file))
+        let data = std::fs::read(file).map_err(|e| e.to_string())?;
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Painter".to_string());
+        Self::from_data(&name, &data)
+    }
+
+    // a new painting that starts with the content of a template file (resized to the given size); the
+    // background fills the parts of the template that are empty or transparent
+    pub fn from_template(
+        name: &str,
+        file: &Path,
+        size: Size,
+        background: Character,
+    ) -> Result<Self, String> {
+        let data = std::fs::read(file).map_err(|e| e.to_string())?;
+        let mut w = Self::from_data(name, &data)?;
+        w.info.title = name.to_string();
+        let h = w.painter;
+        if let Some(p) = w.control_mut(h) {
+            if p.canvas_size() != size {
+                p.set_canvas(
+                    Rect::with_point_and_size(Point::new(0, 0), size),
+                    background,
+                    "Canvas size",
+                );
+            }
+            p.fill_background(background);
+            p.clear_history();
+        }
+        Ok(w)
+    }
+
+    fn from_data(name: &str, data: &[u8]) -> Result<Self, String> {
+        if !project::is_project(data) {
+            let surface = project::surface_from_buffer(data)?;
+            return Self::inner_new(name, PainterControl::with_surface(surface));
+        }
+        let project = project::load(data)?;
+        let mut w = Self::inner_new(name, PainterControl::with_layers(project.layers))?;
+        w.set_document_info(project.info);
+        w.pending_tool_settings = project.tool_settings;
+        Ok(w)
     }
 
+    // must be called after the window was added to the desktop
+    pub fn restore_tool_settings(&mut self) {
+        let settings = std::mem::take(&mut self.pending_tool_settings);
+        if !settings.is_empty() {
+            self.apply_tool_settings(&settings);
+        }
+    }
+
+    // `.srf` files keep only the drawing, any other extension is saved as a project
     pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
-        let h = self.painter;
-        if let Some(p) = self.control(h) {
-            p.save_to_file(path)
-        } else {
-            Err("Painter control not found".to_string())
+        let is_surface = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("srf"));
+        if is_surface {
+            let h = self.painter;
+            return if let Some(p) = self.control(h) {
+                p.save_to_file(path)
+            } else {
+                Err("Painter control not found".to_string())
+            };
+        }
+        let project = self.to_project()?;
+        std::fs::write(path, project::save(&project))
+            .map_err(|e| format!("Failed to save project to file '{}': {}", path.display(), e))
+    }
+
+    fn to_project(&self) -> Result<Project, String> {
+        let Some(p) = self.control(self.painter) else {
+            return Err("Painter control not found".to_string());
+        };
+        let size = p.canvas_size();
+        Ok(Project {
+            info: self.info.clone(),
+            width: size.width,
+            height: size.height,
+            layers: p.layers().to_vec(),
+            tool_settings: self.tool_settings(),
+        })
+    }
+
+    pub fn document_info(&self) -> &DocumentInfo {
+        &self.info
+    }
+
+    pub fn set_document_info(&mut self, info: DocumentInfo) {
+        let h = self.palette;
+        if let Some(bar) = self.control_mut(h) {
+            bar.set_colors(&info.palette);
         }
+        self.info = info;
+    }
+
+    // the visible layers composed into a single surface
+    pub fn surface(&self) -> Option<Surface> {
+        self.control(self.painter).map(|p| p.composite())
     }
 
     pub fn clear_surface(&mut self) {
@@ -175,11 +661,148 @@ impl PainterWindow {
         }
     }
 
+    pub fn canvas_size(&self) -> Option<Size> {
+        self.control(self.painter).map(|p| p.canvas_size())
+    }
+
+    pub fn resize_canvas(&mut self, options: &CanvasSizeOptions) {
+        let h = self.painter;
+        if let Some(p) = self.control_mut(h) {
+            let rect = options.rect(p.canvas_size());
+            p.set_canvas(rect, options.fill, "Canvas size");
+        }
+    }
+
+    pub fn crop_to_selection(&mut self) -> bool {
+        let h = self.painter;
+        self.control_mut(h).is_some_and(|p| p.crop_to_selection())
+    }
+
+    pub fn trim_to_content(&mut self) -> bool {
+        let h = self.painter;
+        self.control_mut(h).is_some_and(|p| p.trim_to_content())
+    }
+
+    // rebuilds the layers panel from the layer stack of the painter
+    fn refresh_layers(&mut self) {
+        let Some(p) = self.control(self.painter) else {
+            return;
+        };
+        let layers: Vec<(String, bool)> = p
+            .layers()
+            .iter()
+            .rev()
+            .map(|l| (l.name.clone(), l.visible))
+            .collect();
+        let active = &p.layers()[p.active_layer()];
+        let (name, locked, opacity) = (active.name.clone(), active.locked, active.opacity);
+        let index = layers.len() - 1 - p.active_layer();
+        let h = self.layers_list;
+        if let Some(list) = self.control_mut(h) {
+            list.clear();
+            for (name, visible) in layers {
+                list.add_item(listbox::Item::new(&name, visible));
+            }
+            list.set_index(index);
+        }
+        let h = self.layer_name;
+        if let Some(t) = self.control_mut(h) {
+            t.set_text(&name);
+        }
+        let h = self.layer_locked;
+        if let Some(c) = self.control_mut(h) {
+            c.set_checked(locked);
+        }
+        let h = self.layer_opacity;
+        if let Some(n) = self.control_mut(h) {
+            n.set_value(opacity as u32);
+        }
+    }
+
+    // rebuilds the history panel (undone steps are listed after the current one)
+    fn refresh_history(&mut self) {
+        let Some(p) = self.control(self.painter) else {
+            return;
+        };
+        let history = p.history();
+        let position = history.position();
+        let steps: Vec<String> = history
+            .steps()
+            .enumerate()
+            .map(|(index, name)| {
+                if index < position {
+                    name.to_string()
+                } else {
+                    format!("{} (undone)", name)
+                }
+            })
+            .collect();
+        let memory = format!("Memory: {} KB", history.memory().div_ceil(1024));
+        let h = self.history_list;
+        if let Some(list) = self.control_mut(h) {
+            list.clear();
+            list.add("Start");
+            for step in steps.iter() {
+                list.add(step);
+            }
+            list.set_index(position);
+        }
+        let h = self.history_memory;
+        if let Some(label) = self.control_mut(h) {
+            label.set_caption(&memory);
+        }
+    }
+
+    fn layer_command(&mut self, handle: Handle<Button>) {
+        let name = self
+            .control(self.layer_name)
+            .map(|t| t.text().trim().to_string())
+            .unwrap_or_default();
+        let (add, duplicate, delete, up, down, merge, rename) = (
+            self.layer_add,
+            self.layer_duplicate,
+            self.layer_delete,
+            self.layer_up,
+            self.layer_down,
+            self.layer_merge,
+            self.layer_rename,
+        );
+        let h = self.painter;
+        let Some(p) = self.control_mut(h) else {
+            return;
+        };
+        if handle == add {
+            let name = format!("Layer {}", p.layers().len() + 1);
+            p.add_layer(&name);
+        } else if handle == duplicate {
+            p.duplicate_layer();
+        } else if handle == delete {
+            p.delete_layer();
+        } else if handle == up {
+            p.move_layer_up();
+        } else if handle == down {
+            p.move_layer_down();
+        } else if handle == merge {
+            p.merge_down();
+        } else if handle == rename && !name.is_empty() {
+            p.rename_layer(&name);
+        }
+        self.refresh_layers();
+    }
+
     fn update_proprties(&mut self) {
+        // selection
+        let selection_nine_slice = self
+            .control(self.selection_nine_slice)
+            .unwrap()
+            .is_checked();
+
         // rect
         let rect_back = self.control(self.rectangle_back).unwrap().color();
         let rect_fore = self.control(self.rectangle_fore).unwrap().color();
         let rect_line_type = self.control(self.rectangle_line_type).unwrap().value();
+        let rect_join = self.control(self.rectangle_join).unwrap().is_checked();
+        let rect_flags = self.char_flags(self.rectangle_flags);
 
         // fill
         let fill_back = self.control(self.fill_back).unwrap().color();
@@ -189,32 +812,531 @@ impl PainterWindow {
             .unwrap()
             .char()
             .unwrap_or(0 as char);
+        let fill_flags = self.char_flags(self.fill_flags);
 
         // line
         let line_back = self.control(self.line_back).unwrap().color();
         let line_fore = self.control(self.line_fore).unwrap().color();
         let line_type = self.control(self.line_type).unwrap().value();
-        let line_vert = self.control(self.line_vert).unwrap().is_selected();
+        let line_direction = if self.control(self.line_vert).unwrap().is_selected() {
+            LineDirection::Vertical
+        } else if self.control(self.line_free).unwrap().is_selected() {
+            LineDirection::Free
+        } else {
+            LineDirection::Horizontal
+        };
+        let line_join = self.control(self.line_join).unwrap().is_checked();
+        let line_flags = self.char_flags(self.line_flags);
+        let line_fixed_char = if self.control(self.line_fixed).unwrap().is_checked() {
+            self.control(self.line_fixed_char).unwrap().char()
+        } else {
+            None
+        };
 
         // text
         let text_fore = self.control(self.text_fore).unwrap().color();
         let text_back = self.control(self.text_back).unwrap().color();
         let text_content = self.control(self.text_content).unwrap().text().to_string();
+        let text_flags = self.char_flags(self.text_flags);
+
+        // pencil
+        let pencil_back = self.control(self.pencil_back).unwrap().color();
+        let pencil_fore = self.control(self.pencil_fore).unwrap().color();
+        let pencil_char = self
+            .control(self.pencil_char)
+            .unwrap()
+            .char()
+            .unwrap_or(0 as char);
+        let pencil_flags = self.char_flags(self.pencil_flags);
+
+        // eraser
+        let eraser_width = self.control(self.eraser_width).unwrap().value();
+        let eraser_height = self.control(self.eraser_height).unwrap().value();
+        let eraser_fore = self.control(self.eraser_fore).unwrap().color();
+        let eraser_back = self.control(self.eraser_back).unwrap().color();
+        // a fully transparent blank empties the cells (the layers below show through)
+        let eraser_blank = if eraser_fore == Color::Transparent && eraser_back == Color::Transparent
+        {
+            layer::EMPTY
+        } else {
+            Character::new(
+                self.control(self.eraser_char)
+                    .unwrap()
+                    .char()
+                    .unwrap_or(' '),
+                eraser_fore,
+                eraser_back,
+                self.char_flags(self.eraser_flags),
+            )
+        };
+
+        // flood fill
+        let floodfill_back = self.control(self.floodfill_back).unwrap().color();
+        let floodfill_fore = self.control(self.floodfill_fore).unwrap().color();
+        let floodfill_char = self
+            .control(self.floodfill_char)
+            .unwrap()
+            .char()
+            .unwrap_or(0 as char);
+        let floodfill_match = self.control(self.floodfill_match).unwrap().value();
+        let floodfill_diagonal = self.control(self.floodfill_diagonal).unwrap().is_checked();
+        let floodfill_flags = self.char_flags(self.floodfill_flags);
+
+        // ellipse
+        let ellipse_back = self.control(self.ellipse_back).unwrap().color();
+        let ellipse_fore = self.control(self.ellipse_fore).unwrap().color();
+        let ellipse_char = self
+            .control(self.ellipse_char)
+            .unwrap()
+            .char()
+            .unwrap_or(0 as char);
+        let ellipse_filled = self.control(self.ellipse_filled).unwrap().is_checked();
+        let ellipse_aspect = self.control(self.ellipse_aspect).unwrap().is_checked();
+        let ellipse_flags = self.char_flags(self.ellipse_flags);
+
+        // connector
+        let connector_back = self.control(self.connector_back).unwrap().color();
+        let connector_fore = self.control(self.connector_fore).unwrap().color();
+        let connector_line_type = self.control(self.connector_line_type).unwrap().value();
+        let connector_start = self.control(self.connector_start).unwrap().value();
+        let connector_end = self.control(self.connector_end).unwrap().value();
+        let connector_rounded = self.control(self.connector_rounded).unwrap().is_checked();
+        let connector_flags = self.char_flags(self.connector_flags);
 
         // update all properties
         let h = self.painter;
         if let Some(p) = self.control_mut(h) {
-            p.update_rectangle_properties(rect_fore, rect_back, rect_line_type);
-            p.update_fillrectangle_properties(fill_fore, fill_back, fill_char, CharFlags::None);
-            p.update_line_properties(line_fore, line_back, line_type, line_vert);
-            p.update_text_properties(text_content, text_fore, text_back, CharFlags::None);
+            p.update_selection_properties(selection_nine_slice);
+            p.update_rectangle_properties(
+                rect_fore,
+                rect_back,
+                rect_flags,
+                rect_line_type,
+                rect_join,
+            );
+            p.update_fillrectangle_properties(fill_fore, fill_back, fill_char, fill_flags);
+            p.update_line_properties(
+                line_fore,
+                line_back,
+                line_flags,
+                line_type,
+                line_direction,
+                line_fixed_char,
+                line_join,
+            );
+            p.update_text_properties(text_content, text_fore, text_back, text_flags);
+            p.update_pencil_properties(pencil_fore, pencil_back, pencil_char, pencil_flags);
+            p.update_eraser_properties(eraser_width, eraser_height, eraser_blank);
+            p.update_floodfill_properties(
+                floodfill_fore,
+                floodfill_back,
+                floodfill_char,
+                floodfill_flags,
+                floodfill_match,
+                floodfill_diagonal,
+            );
+            p.update_ellipse_properties(
+                ellipse_fore,
+                ellipse_back,
+                ellipse_char,
+                ellipse_flags,
+                ellipse_filled,
+                ellipse_aspect,
+            );
+            p.update_connector_properties(
+                connector_fore,
+                connector_back,
+                connector_flags,
+                connector_line_type,
+                connector_start,
+                connector_end,
+                connector_rounded,
+            );
+        }
+    }
+
+    // color pickers, char picker and style check boxes of a tool panel
+    fn panel_controls(
+        &self,
+        panel: usize,
+    ) -> Option<(
+        Handle<ColorPicker>,
+        Handle<ColorPicker>,
+        Handle<CharPicker>,
+        FlagControls,
+    )> {
+        match panel {
+            2 => Some((
+                self.rectangle_fore,
+                self.rectangle_back,
+                Handle::None,
+                self.rectangle_flags,
+            )),
+            3 => Some((
+                self.fill_fore,
+                self.fill_back,
+                self.fill_char,
+                self.fill_flags,
+            )),
+            4 => Some((
+                self.line_fore,
+                self.line_back,
+                self.line_fixed_char,
+                self.line_flags,
+            )),
+            5 => Some((
+                self.text_fore,
+                self.text_back,
+                Handle::None,
+                self.text_flags,
+            )),
+            6 => Some((
+                self.pencil_fore,
+                self.pencil_back,
+                self.pencil_char,
+                self.pencil_flags,
+            )),
+            7 => Some((
+                self.eraser_fore,
+                self.eraser_back,
+                self.eraser_char,
+                self.eraser_flags,
+            )),
+            8 => Some((
+                self.floodfill_fore,
+                self.floodfill_back,
+                self.floodfill_char,
+                self.floodfill_flags,
+            )),
+            9 => Some((
+                self.ellipse_fore,
+                self.ellipse_back,
+                self.ellipse_char,
+                self.ellipse_flags,
+            )),
+            10 => Some((
+                self.connector_fore,
+                self.connector_back,
+                Handle::None,
+                self.connector_flags,
+            )),
+            _ => None,
         }
     }
+
+    // colors, characters and styles of every tool, plus the active tool
+    fn tool_settings(&self) -> Vec<(String, String)> {
+        let mut settings = Vec::new();
+        if let Some(panel) = self.control(self.acc).and_then(|acc| acc.current_panel()) {
+            if let Some(name) = TOOL_NAMES.get(panel) {
+                settings.push(("tool".to_string(), name.to_string()));
+            }
+        }
+        for (panel, name) in TOOL_NAMES.iter().enumerate() {
+            let Some((fore, back, char_picker, flags)) = self.panel_controls(panel) else {
+                continue;
+            };
+            if let Some(c) = self.control(fore) {
+                settings.push((
+                    format!("{}.fore", name),
+                    project::color_to_string(c.color()),
+                ));
+            }
+            if let Some(c) = self.control(back) {
+                settings.push((
+                    format!("{}.back", name),
+                    project::color_to_string(c.color()),
+                ));
+            }
+            if let Some(ch) = self.control(char_picker).and_then(|c| c.char()) {
+                settings.push((format!("{}.char", name), ch.to_string()));
+            }
+            settings.push((
+                format!("{}.flags", name),
+                self.char_flags(flags).get_value().to_string(),
+            ));
+        }
+        // the options that only some of the tools have
+        let line_direction = if self
+            .control(self.line_vert)
+            .is_some_and(|c| c.is_selected())
+        {
+            "vertical"
+        } else if self
+            .control(self.line_free)
+            .is_some_and(|c| c.is_selected())
+        {
+            "free"
+        } else {
+            "horizontal"
+        };
+        let size = |h: Handle<NumericSelector<u32>>| self.control(h).map(|c| c.value().to_string());
+        let options = [
+            (
+                "selection.nine_slice",
+                self.checked_setting(self.selection_nine_slice),
+            ),
+            (
+                "rectangle.line_type",
+                self.selected_setting(self.rectangle_line_type),
+            ),
+            ("rectangle.join", self.checked_setting(self.rectangle_join)),
+            ("line.line_type", self.selected_setting(self.line_type)),
+            ("line.direction", Some(line_direction.to_string())),
+            ("line.fixed", self.checked_setting(self.line_fixed)),
+            ("line.join", self.checked_setting(self.line_join)),
+            ("eraser.width", size(self.eraser_width)),
+            ("eraser.height", size(self.eraser_height)),
+            (
+                "floodfill.match",
+                self.selected_setting(self.floodfill_match),
+            ),
+            (
+                "floodfill.diagonal",
+                self.checked_setting(self.floodfill_diagonal),
+            ),
+            ("ellipse.filled", self.checked_setting(self.ellipse_filled)),
+            ("ellipse.aspect", self.checked_setting(self.ellipse_aspect)),
+            (
+                "connector.line_type",
+                self.selected_setting(self.connector_line_type),
+            ),
+            (
+                "connector.start",
+                self.selected_setting(self.connector_start),
+            ),
+            ("connector.end", self.selected_setting(self.connector_end)),
+            (
+                "connector.rounded",
+                self.checked_setting(self.connector_rounded),
+            ),
+        ];
+        for (key, value) in options {
+            if let Some(value) = value {
+                settings.push((key.to_string(), value));
+            }
+        }
+        settings
+    }
+
+    // "true" / "false"
+    fn checked_setting(&self, h: Handle<CheckBox>) -> Option<String> {
+        self.control(h).map(|c| c.is_checked().to_string())
+    }
+
+    // the name of the selected value
+    fn selected_setting<T: EnumSelector + Copy + Eq + 'static>(
+        &self,
+        h: Handle<Selector<T>>,
+    ) -> Option<String> {
+        self.control(h)
+            .and_then(|c| c.try_value())
+            .map(|v| v.name().to_string())
+    }
+
+    fn apply_checked_setting(&mut self, h: Handle<CheckBox>, value: &str) {
+        if let (Ok(checked), Some(c)) = (value.parse(), self.control_mut(h)) {
+            c.set_checked(checked);
+        }
+    }
+
+    fn apply_selected_setting<T: EnumSelector + Copy + Eq + 'static>(
+        &mut self,
+        h: Handle<Selector<T>>,
+        value: &str,
+    ) {
+        let selected = (0..T::COUNT)
+            .filter_map(T::from_index)
+            .find(|v| v.name() == value);
+        if let (Some(selected), Some(c)) = (selected, self.control_mut(h)) {
+            c.set_value(selected);
+        }
+    }
+
+    // the options that only some of the tools have (returns false for the other keys)
+    fn apply_tool_option(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "selection.nine_slice" => self.apply_checked_setting(self.selection_nine_slice, value),
+            "rectangle.line_type" => self.apply_selected_setting(self.rectangle_line_type, value),
+            "rectangle.join" => self.apply_checked_setting(self.rectangle_join, value),
+            "line.line_type" => self.apply_selected_setting(self.line_type, value),
+            "line.direction" => {
+                let h = match value {
+                    "vertical" => self.line_vert,
+                    "horizontal" => self.line_horizontal,
+                    "free" => self.line_free,
+                    _ => Handle::None,
+                };
+                if let Some(c) = self.control_mut(h) {
+                    c.set_selected();
+                }
+            }
+            "line.fixed" => self.apply_checked_setting(self.line_fixed, value),
+            "line.join" => self.apply_checked_setting(self.line_join, value),
+            "eraser.width" | "eraser.height" => {
+                let h = if key == "eraser.width" {
+                    self.eraser_width
+                } else {
+                    self.eraser_height
+                };
+                if let (Ok(size), Some(c)) = (value.parse(), self.control_mut(h)) {
+                    c.set_value(size);
+                }
+            }
+            "floodfill.match" => self.apply_selected_setting(self.floodfill_match, value),
+            "floodfill.diagonal" => self.apply_checked_setting(self.floodfill_diagonal, value),
+            "ellipse.filled" => self.apply_checked_setting(self.ellipse_filled, value),
+            "ellipse.aspect" => self.apply_checked_setting(self.ellipse_aspect, value),
+            "connector.line_type" => self.apply_selected_setting(self.connector_line_type, value),
+            "connector.start" => self.apply_selected_setting(self.connector_start, value),
+            "connector.end" => self.apply_selected_setting(self.connector_end, value),
+            "connector.rounded" => self.apply_checked_setting(self.connector_rounded, value),
+            _ => return false,
+        }
+        true
+    }
+
+    // unknown keys and invalid values are ignored
+    fn apply_tool_settings(&mut self, settings: &[(String, String)]) {
+        for (key, value) in settings {
+            if key == "tool" {
+                if let Some(panel) = TOOL_NAMES.iter().position(|n| n == value) {
+                    let h = self.acc;
+                    if let Some(acc) = self.control_mut(h) {
+                        acc.set_current_panel(panel);
+                    }
+                    // `set_current_panel` does not raise `on_panel_changed`
+                    let h = self.painter;
+                    if let (Some(d), Some(p)) = (drawing_object(panel), self.control_mut(h)) {
+                        p.write_current_object();
+                        p.reset(d);
+                    }
+                }
+                continue;
+            }
+            if self.apply_tool_option(key, value) {
+                continue;
+            }
+            let Some((name, property)) = key.split_once('.') else {
+                continue;
+            };
+            let Some(panel) = TOOL_NAMES.iter().position(|n| *n == name) else {
+                continue;
+            };
+            let Some((fore, back, char_picker, flags)) = self.panel_controls(panel) else {
+                continue;
+            };
+            match property {
+                "fore" | "back" => {
+                    let h = if property == "fore" { fore } else { back };
+                    if let (Some(color), Some(c)) =
+                        (project::color_from_string(value), self.control_mut(h))
+                    {
+                        c.set_color(color);
+                    }
+                }
+                "char" => {
+                    if let (Some(ch), Some(c)) =
+                        (value.chars().next(), self.control_mut(char_picker))
+                    {
+                        c.select_char(ch);
+                    }
+                }
+                "flags" => {
+                    let Some(value) = value.parse().ok().and_then(CharFlags::from_value) else {
+                        continue;
+                    };
+                    for (h, flag) in [
+                        (flags.bold, CharFlags::Bold),
+                        (flags.italic, CharFlags::Italic),
+                        (flags.underline, CharFlags::Underline),
+                    ] {
+                        if let Some(c) = self.control_mut(h) {
+                            c.set_checked(value.contains(flag));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.update_proprties();
+    }
+
+    // loads the attributes of a sampled cell into the controls of the active panel
+    fn apply_picked_character(&mut self, ch: Character) {
+        let Some(panel) = self.control(self.acc).and_then(|acc| acc.current_panel()) else {
+            return;
+        };
+        let Some((fore, back, char_picker, flags)) = self.panel_controls(panel) else {
+            return;
+        };
+        if let Some(c) = self.control_mut(fore) {
+            c.set_color(ch.foreground);
+        }
+        if let Some(c) = self.control_mut(back) {
+            c.set_color(ch.background);
+        }
+        if ch.code != 0 as char {
+            if let Some(c) = self.control_mut(char_picker) {
+                c.select_char(ch.code);
+            }
+            // the fixed character of the line is used only when "Fixed" is checked
+            if char_picker == self.line_fixed_char {
+                let h = self.line_fixed;
+                if let Some(c) = self.control_mut(h) {
+                    c.set_checked(true);
+                }
+            }
+        }
+        for (h, flag) in [
+            (flags.bold, CharFlags::Bold),
+            (flags.italic, CharFlags::Italic),
+            (flags.underline, CharFlags::Underline),
+        ] {
+            if let Some(c) = self.control_mut(h) {
+                c.set_checked(ch.flags.contains(flag));
+            }
+        }
+        self.update_proprties();
+    }
+
+    fn char_flags(&self, controls: FlagControls) -> CharFlags {
+        let mut flags = CharFlags::None;
+        if self.control(controls.bold).unwrap().is_checked() {
+            flags |= CharFlags::Bold;
+        }
+        if self.control(controls.italic).unwrap().is_checked() {
+            flags |= CharFlags::Italic;
+        }
+        if self.control(controls.underline).unwrap().is_checked() {
+            flags |= CharFlags::Underline;
+        }
+        flags
+    }
 }
 
 impl MenuEvents for PainterWindow {
     fn on_update_menubar(&self, menubar: &mut MenuBar) {
-        menubar.add(self.menu, 0);
+        menubar.add(self.menu, 1);
+    }
+
+    fn on_select(
+        &mut self,
+        _menu: Handle<Menu>,
+        _item: Handle<menu::SingleChoice>,
+        command: painterwindow::Commands,
+    ) {
+        let mode = match command {
+            painterwindow::Commands::PasteOpaque => PasteMode::Opaque,
+            painterwindow::Commands::PasteSkipSpaces => PasteMode::SkipSpaces,
+            painterwindow::Commands::PasteSkipBackground => PasteMode::SkipBackground,
+            painterwindow::Commands::PasteForegroundOnly => PasteMode::ForegroundOnly,
+            _ => return,
+        };
+        let h = self.painter;
+        if let Some(p) = self.control_mut(h) {
+            p.set_paste_mode(mode);
+        }
     }
 
     fn on_command(
@@ -236,6 +1358,12 @@ impl MenuEvents for PainterWindow {
             painterwindow::Commands::Char100 => {
                 //self.set_drawing_char('█');
             }
+            painterwindow::Commands::Paste => {
+                let h = self.painter;
+                if let Some(p) = self.control_mut(h) {
+                    p.paste_from_clipboard(p.paste_mode());
+                }
+            }
             _ => {}
         }
     }
@@ -248,16 +1376,7 @@ impl AccordionEvents for PainterWindow {
         new_panel_index: u32,
         _: u32,
     ) -> EventProcessStatus {
-        let d = match new_panel_index {
-            0 => Some(DrawingObject::Selection(SelectionObject::default())),
-            1 => Some(DrawingObject::Move(MoveObject::default())),
-            2 => Some(DrawingObject::Rectangle(RectangleObject::default())),
-            3 => Some(DrawingObject::FillRectangle(FillRectangleObject::default())),
-            4 => Some(DrawingObject::Line(LineObject::default())),
-            5 => Some(DrawingObject::Text(TextObject::default())),
-            _ => None,
-        };
-        if let Some(drawing_object) = d {
+        if let Some(drawing_object) = drawing_object(new_panel_index as usize) {
             let h = self.painter;
             if let Some(p) = self.control_mut(h) {
                 p.write_current_object();
@@ -271,6 +1390,57 @@ impl AccordionEvents for PainterWindow {
     }
 }
 
+impl PainterControlEvents for PainterWindow {
+    fn on_event(
+        &mut self,
+        handle: Handle<PainterControl>,
+        event: paintercontrol::Events,
+    ) -> EventProcessStatus {
+        match event {
+            paintercontrol::Events::CharacterPicked => {
+                let picked = self.control(handle).and_then(|p| p.picked_character());
+                if let Some(ch) = picked {
+                    self.apply_picked_character(ch);
+                }
+                EventProcessStatus::Processed
+            }
+            paintercontrol::Events::HistoryChanged => {
+                self.refresh_layers();
+                self.refresh_history();
+                EventProcessStatus::Processed
+            }
+        }
+    }
+}
+
+impl PaletteBarEvents for PainterWindow {
+    fn on_event(
+        &mut self,
+        handle: Handle<PaletteBar>,
+        event: palettebar::Events,
+    ) -> EventProcessStatus {
+        match event {
+            palettebar::Events::ColorPicked => {
+                let Some((color, foreground)) = self.control(handle).and_then(|b| b.picked())
+                else {
+                    return EventProcessStatus::Ignored;
+                };
+                let Some(panel) = self.control(self.acc).and_then(|acc| acc.current_panel()) else {
+                    return EventProcessStatus::Ignored;
+                };
+                let Some((fore, back, _, _)) = self.panel_controls(panel) else {
+                    return EventProcessStatus::Ignored;
+                };
+                if let Some(c) = self.control_mut(if foreground { fore } else { back }) {
+                    c.set_color(color);
+                }
+                self.update_proprties();
+                EventProcessStatus::Processed
+            }
+        }
+    }
+}
+
 impl ColorPickerEvents for PainterWindow {
     fn on_color_changed(&mut self, _: Handle<ColorPicker>, _: Color) -> EventProcessStatus {
         self.update_proprties();
@@ -283,6 +1453,36 @@ impl RadioBoxEvents for PainterWindow {
         EventProcessStatus::Processed
     }
 }
+impl CheckBoxEvents for PainterWindow {
+    fn on_status_changed(&mut self, handle: Handle<CheckBox>, checked: bool) -> EventProcessStatus {
+        if handle == self.layer_locked {
+            let h = self.painter;
+            if let Some(p) = self.control_mut(h) {
+                p.set_layer_locked(checked);
+            }
+        } else {
+            self.update_proprties();
+        }
+        EventProcessStatus::Processed
+    }
+}
+impl NumericSelectorEvents<u32> for PainterWindow {
+    fn on_value_changed(
+        &mut self,
+        handle: Handle<NumericSelector<u32>>,
+        value: u32,
+    ) -> EventProcessStatus {
+        if handle == self.layer_opacity {
+            let h = self.painter;
+            if let Some(p) = self.control_mut(h) {
+                p.set_layer_opacity(value as u8);
+            }
+        } else {
+            self.update_proprties();
+        }
+        EventProcessStatus::Processed
+    }
+}
 impl CharPickerEvents for PainterWindow {
     fn on_char_changed(&mut self, _: Handle<CharPicker>, _: Option<char>) -> EventProcessStatus {
         self.update_proprties();
@@ -300,8 +1500,82 @@ impl SelectorEvents<LineType> for PainterWindow {
     }
 }
 
+impl SelectorEvents<FillMatch> for PainterWindow {
+    fn on_selection_changed(
+        &mut self,
+        _: Handle<Selector<FillMatch>>,
+        _: Option<FillMatch>,
+    ) -> EventProcessStatus {
+        self.update_proprties();
+        EventProcessStatus::Processed
+    }
+}
+
+impl SelectorEvents<ArrowHead> for PainterWindow {
+    fn on_selection_changed(
+        &mut self,
+        _: Handle<Selector<ArrowHead>>,
+        _: Option<ArrowHead>,
+    ) -> EventProcessStatus {
+        self.update_proprties();
+        EventProcessStatus::Processed
+    }
+}
+
+impl ListBoxEvents for PainterWindow {
+    fn on_current_item_changed(
+        &mut self,
+        handle: Handle<ListBox>,
+        index: usize,
+    ) -> EventProcessStatus {
+        let h = self.painter;
+        if handle == self.history_list {
+            // item `index` is the painting after `index` steps
+            if let Some(p) = self.control_mut(h) {
+                p.jump_to(index);
+            }
+            return EventProcessStatus::Processed;
+        }
+        if let Some(p) = self.control_mut(h) {
+            if index < p.layers().len() {
+                p.set_active_layer(p.layers().len() - 1 - index);
+            }
+        }
+        self.refresh_layers();
+        EventProcessStatus::Processed
+    }
+
+    fn on_item_checked(
+        &mut self,
+        _: Handle<ListBox>,
+        index: usize,
+        checked: bool,
+    ) -> EventProcessStatus {
+        let h = self.painter;
+        if let Some(p) = self.control_mut(h) {
+            if index < p.layers().len() {
+                p.set_layer_visible(p.layers().len() - 1 - index, checked);
+            }
+        }
+        EventProcessStatus::Processed
+    }
+}
+
 impl ButtonEvents for PainterWindow {
     fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
-        EventProcessStatus::Ignored
+        let transform = self
+            .selection_transforms
+            .iter()
+            .find(|(h, _)| *h == handle)
+            .map(|(_, t)| *t);
+        if let Some(transform) = transform {
+            let h = self.painter;
+            if let Some(p) = self.control_mut(h) {
+                p.transform_selection(transform);
+            }
+            return EventProcessStatus::Processed;
+        }
+        self.layer_command(handle);
+        EventProcessStatus::Processed
     }
 }
@@ -0,0 +1,64 @@
+use appcui::prelude::*;
+
+// width of a color swatch
+const SWATCH_WIDTH: i32 = 3;
+
+// The document palette shown as a row of color swatches: a left click picks the fore color of the active
+// tool, a right click its back color.
+#[CustomControl(overwrite = OnPaint + OnMouseEvent, emit = ColorPicked)]
+pub struct PaletteBar {
+    colors: Vec<Color>,
+    // the last picked color and true if it was picked as a fore color
+    picked: Option<(Color, bool)>,
+}
+
+impl PaletteBar {
+    pub fn new(colors: &[Color], layout: Layout) -> Self {
+        Self {
+            base: ControlBase::new(layout, true),
+            colors: colors.to_vec(),
+            picked: None,
+        }
+    }
+
+    pub fn set_colors(&mut self, colors: &[Color]) {
+        self.colors = colors.to_vec();
+    }
+
+    pub fn picked(&self) -> Option<(Color, bool)> {
+        self.picked
+    }
+}
+
+impl OnPaint for PaletteBar {
+    fn on_paint(&self, surface: &mut Surface, theme: &Theme) {
+        surface.clear(Character::with_attributes(' ', theme.window.normal));
+        for (index, color) in self.colors.iter().enumerate() {
+            let x = index as i32 * SWATCH_WIDTH;
+            let ch = match color {
+                Color::Transparent => {
+                    Character::new('░', Color::Gray, Color::Black, CharFlags::None)
+                }
+                _ => Character::new(' ', Color::Black, *color, CharFlags::None),
+            };
+            surface.fill_horizontal_line_with_size(x, 0, SWATCH_WIDTH as u32, ch);
+        }
+    }
+}
+
+impl OnMouseEvent for PaletteBar {
+    fn on_mouse_event(&mut self, event: &MouseEvent) -> EventProcessStatus {
+        let MouseEvent::Pressed(data) = event else {
+            return EventProcessStatus::Ignored;
+        };
+        let Some(color) = usize::try_from(data.x / SWATCH_WIDTH)
+            .ok()
+            .and_then(|index| self.colors.get(index))
+        else {
+            return EventProcessStatus::Ignored;
+        };
+        self.picked = Some((*color, data.button != MouseButton::Right));
+        self.raise_event(palettebar::Events::ColorPicked);
+        EventProcessStatus::Processed
+    }
+}
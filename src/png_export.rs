@@ -0,0 +1,126 @@
+use appcui::prelude::*;
+
+use crate::bitmap_font::{self, HEIGHT, WIDTH};
+use crate::colors;
+
+#[derive(Copy, Clone)]
+pub struct PngExportOptions {
+    pub scale: u32,
+}
+
+// colors used for transparent cells (the default console colors)
+const DEFAULT_FORE: (u8, u8, u8) = colors::PALETTE[7];
+const DEFAULT_BACK: (u8, u8, u8) = colors::PALETTE[0];
+
+// glyph of a character with its style flags applied
+fn styled_glyph(ch: &Character) -> bitmap_font::Glyph {
+    let mut glyph = bitmap_font::glyph(ch.code);
+    if ch.flags.contains(CharFlags::Italic) {
+        // shear: the top rows are shifted to the right
+        for (y, row) in glyph.iter_mut().enumerate() {
+            *row >>= (HEIGHT - 1 - y) / 5;
+        }
+    }
+    if ch.flags.contains(CharFlags::Bold) {
+        for row in glyph.iter_mut() {
+            *row |= *row >> 1;
+        }
+    }
+    if ch.flags.contains(CharFlags::Underline) {
+        glyph[HEIGHT - 2] = 0xFF;
+    }
+    glyph
+}
+
+// renders the surface as RGB pixels (3 bytes per pixel), returning (width, height, pixels)
+fn render(surface: &Surface, scale: u32) -> (u32, u32, Vec<u8>) {
+    let size = surface.size();
+    let scale = scale.max(1) as usize;
+    let cell_w = WIDTH * scale;
+    let cell_h = HEIGHT * scale;
+    let width = size.width as usize * cell_w;
+    let height = size.height as usize * cell_h;
+    let mut pixels = vec![0u8; width * height * 3];
+    for cy in 0..size.height as usize {
+        for cx in 0..size.width as usize {
+            let Some(ch) = surface.char(cx as i32, cy as i32) else {
+                continue;
+            };
+            let fore = colors::rgb(ch.foreground).unwrap_or(DEFAULT_FORE);
+            let back = colors::rgb(ch.background).unwrap_or(DEFAULT_BACK);
+            let glyph = styled_glyph(ch);
+            for py in 0..cell_h {
+                let row = glyph[py / scale];
+                let start = ((cy * cell_h + py) * width + cx * cell_w) * 3;
+                for px in 0..cell_w {
+                    let (r, g, b) = if row & (0x80 >> (px / scale)) != 0 {
+                        fore
+                    } else {
+                        back
+                    };
+                    let offset = start + px * 3;
+                    pixels[offset..offset + 3].copy_from_slice(&[r, g, b]);
+                }
+            }
+        }
+    }
+    (width as u32, height as u32, pixels)
+}
+
+pub fn export(surface: &Surface, options: &PngExportOptions) -> Result<Vec<u8>, String> {
+    let (width, height, pixels) = render(surface, options.scale);
+    if width == 0 || height == 0 {
+        return Err("the painting is empty".to_string());
+    }
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+#[ModalWindow(events = ButtonEvents, response = PngExportOptions)]
+pub struct PngExportDialog {
+    scale: Handle<NumericSelector<u32>>,
+    export: Handle<Button>,
+}
+
+impl PngExportDialog {
+    pub fn new() -> Self {
+        let mut w = Self {
+            base: ModalWindow::new(
+                "Export as PNG",
+                layout!("a:c,w:44,h:7"),
+                window::Flags::None,
+            ),
+            scale: Handle::None,
+            export: Handle::None,
+        };
+        w.add(label!("'Scale:',x:1,y:1,w:14,h:1"));
+        w.scale = w.add(numericselector!(
+            "u32,value:1,min:1,max:8,step:1,x:16,y:1,w:25"
+        ));
+        w.export = w.add(button!("&Export,x:8,y:3,w:12"));
+        w.add(button!("&Cancel,x:22,y:3,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for PngExportDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.export {
+            let options = PngExportOptions {
+                scale: self.control(self.scale).unwrap().value(),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
@@ -0,0 +1,521 @@
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+use appcui::prelude::*;
+
+use crate::colors;
+use crate::layer::Cells;
+use crate::layer::Layer;
+
+// AsciiPainter project file (.apj)
+//
+// All numbers are little-endian.
+//
+//   header   "APRJ", major version (u16), minor version (u16)
+//   chunks   tag (4 bytes), payload length (u32), payload
+//
+// Chunks of version 1.0:
+//   META     title (string), author (string)
+//   SIZE     canvas width (u32), canvas height (u32)
+//   PALT     number of colors (u16), colors
+//   TOOL     number of settings (u16), key (string) and value (string) for every setting
+//   LAYR     name (string), visible (u8), opacity in percents (u8), locked (u8), surface (u32 length + `.srf` data)
+//
+// Strings are stored as a u32 length followed by UTF-8 bytes. A color is a single byte (0..=15 for the
+// console colors, 16 for transparent) or 17 followed by the red, green and blue bytes.
+//
+// Layers are stored bottom to top. Readers skip chunks they do not know and ignore extra bytes at the end
+// of a chunk, so a minor version may only add chunks or append fields. A new major version means that
+// older readers can not open the file.
+
+const MAGIC: &[u8; 4] = b"APRJ";
+const VERSION_MAJOR: u16 = 1;
+const VERSION_MINOR: u16 = 0;
+
+#[derive(Clone)]
+pub struct DocumentInfo {
+    pub title: String,
+    pub author: String,
+    pub palette: Vec<Color>,
+}
+
+impl DocumentInfo {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            author: String::new(),
+            palette: (0..16).map(colors::named).collect(),
+        }
+    }
+}
+
+pub struct Project {
+    pub info: DocumentInfo,
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<Layer>,
+    pub tool_settings: Vec<(String, String)>,
+}
+
+pub fn is_project(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+// text form of a color, used for the tool settings
+pub fn color_to_string(color: Color) -> String {
+    match color {
+        Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        _ => color.name().to_string(),
+    }
+}
+
+pub fn color_from_string(text: &str) -> Option<Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::RGB(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ));
+    }
+    (0..=16)
+        .filter_map(Color::from_value)
+        .find(|c| c.name() == text)
+}
+
+fn write_u16(output: &mut Vec<u8>, value: u16) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(output: &mut Vec<u8>, value: u32) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_string(output: &mut Vec<u8>, text: &str) {
+    write_u32(output, text.len() as u32);
+    output.extend_from_slice(text.as_bytes());
+}
+
+fn write_color(output: &mut Vec<u8>, color: Color) {
+    match color {
+        Color::Transparent => output.push(16),
+        Color::RGB(r, g, b) => output.extend_from_slice(&[17, r, g, b]),
+        _ => output.push(colors::index(color).unwrap_or(0) as u8),
+    }
+}
+
+fn write_chunk(output: &mut Vec<u8>, tag: &[u8; 4], payload: &[u8]) {
+    output.extend_from_slice(tag);
+    write_u32(output, payload.len() as u32);
+    output.extend_from_slice(payload);
+}
+
+pub fn save(project: &Project) -> Vec<u8> {
+    let mut output = Vec::new();
+    output.extend_from_slice(MAGIC);
+    write_u16(&mut output, VERSION_MAJOR);
+    write_u16(&mut output, VERSION_MINOR);
+
+    let mut payload = Vec::new();
+    write_string(&mut payload, &project.info.title);
+    write_string(&mut payload, &project.info.author);
+    write_chunk(&mut output, b"META", &payload);
+
+    payload.clear();
+    write_u32(&mut payload, project.width);
+    write_u32(&mut payload, project.height);
+    write_chunk(&mut output, b"SIZE", &payload);
+
+    payload.clear();
+    write_u16(&mut payload, project.info.palette.len() as u16);
+    for color in project.info.palette.iter() {
+        write_color(&mut payload, *color);
+    }
+    write_chunk(&mut output, b"PALT", &payload);
+
+    payload.clear();
+    write_u16(&mut payload, project.tool_settings.len() as u16);
+    for (key, value) in project.tool_settings.iter() {
+        write_string(&mut payload, key);
+        write_string(&mut payload, value);
+    }
+    write_chunk(&mut output, b"TOOL", &payload);
+
+    let mut surface_data = Vec::new();
+    for layer in project.layers.iter() {
+        payload.clear();
+        write_string(&mut payload, &layer.name);
+        payload.push(layer.visible as u8);
+        payload.push(layer.opacity.min(100));
+        payload.push(layer.locked as u8);
+        layer
+            .cells
+            .to_surface()
+            .serialize_to_buffer(&mut surface_data);
+        write_u32(&mut payload, surface_data.len() as u32);
+        payload.extend_from_slice(&surface_data);
+        write_chunk(&mut output, b"LAYR", &payload);
+    }
+    output
+}
+
+// width and height from the header of a `.srf` buffer
+pub fn surface_size(data: &[u8]) -> Result<(u32, u32), String> {
+    if data.len() < 12 || &data[0..3] != b"SRF" {
+        return Err("not a surface".to_string());
+    }
+    if data[3] != 1 {
+        return Err("unsupported surface version".to_string());
+    }
+    let width = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+    let height = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+    Ok((width, height))
+}
+
+// `Surface::from_buffer` panics on invalid characters, flags or colors, so the cells are checked first
+pub fn surface_from_buffer(data: &[u8]) -> Result<Surface, String> {
+    let (width, height) = surface_size(data)?;
+    let mut reader = Reader { data, pos: 12 };
+    for _ in 0..width as u64 * height as u64 {
+        char::from_u32(reader.u32()?).ok_or_else(|| "invalid character".to_string())?;
+        CharFlags::from_value(reader.u16()?)
+            .ok_or_else(|| "invalid character flags".to_string())?;
+        reader.color()?;
+        reader.color()?;
+    }
+    let surface = Surface::from_buffer(data)?;
+    let size = surface.size();
+    if size.width != width || size.height != height {
+        return Err(format!("invalid surface size ({}x{})", width, height));
+    }
+    Ok(surface)
+}
+
+fn read_bytes(file: &mut impl Read, count: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0; count];
+    file.read_exact(&mut buffer).map_err(|e| e.to_string())?;
+    Ok(buffer)
+}
+
+// canvas size of a project or `.srf` file, read from its header (the layers are skipped)
+pub fn read_canvas_size(file: &mut (impl Read + Seek)) -> Result<(u32, u32), String> {
+    let header = read_bytes(file, 8)?;
+    if !is_project(&header) {
+        let mut header = header;
+        header.extend(read_bytes(file, 4)?);
+        return surface_size(&header);
+    }
+    if u16::from_le_bytes([header[4], header[5]]) > VERSION_MAJOR {
+        return Err("the project was saved by a newer version of AsciiPainter".to_string());
+    }
+    loop {
+        let chunk = read_bytes(file, 8)?;
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        if &chunk[0..4] == b"SIZE" {
+            let mut reader = Reader {
+                data: &read_bytes(file, 8)?,
+                pos: 0,
+            };
+            return Ok((reader.u32()?, reader.u32()?));
+        }
+        file.seek(SeekFrom::Current(len as i64))
+            .map_err(|e| e.to_string())?;
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.data.len() - self.pos < count {
+            return Err("unexpected end of file".to_string());
+        }
+        let result = &self.data[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(result)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| "invalid text".to_string())
+    }
+
+    fn color(&mut self) -> Result<Color, String> {
+        match self.u8()? {
+            17 => {
+                let b = self.bytes(3)?;
+                Ok(Color::RGB(b[0], b[1], b[2]))
+            }
+            value => Color::from_value(value as i32).ok_or_else(|| "invalid color".to_string()),
+        }
+    }
+}
+
+fn read_chunk(project: &mut Project, tag: &[u8], chunk: &mut Reader) -> Result<(), String> {
+    match tag {
+        b"META" => {
+            project.info.title = chunk.string()?;
+            project.info.author = chunk.string()?;
+        }
+        b"SIZE" => {
+            project.width = chunk.u32()?;
+            project.height = chunk.u32()?;
+        }
+        b"PALT" => {
+            let count = chunk.u16()?;
+            project.info.palette = (0..count)
+                .map(|_| chunk.color())
+                .collect::<Result<_, _>>()?;
+        }
+        b"TOOL" => {
+            let count = chunk.u16()?;
+            for _ in 0..count {
+                let key = chunk.string()?;
+                let value = chunk.string()?;
+                project.tool_settings.push((key, value));
+            }
+        }
+        b"LAYR" => {
+            let name = chunk.string()?;
+            let visible = chunk.u8()? != 0;
+            let opacity = chunk.u8()?.min(100);
+            let locked = chunk.u8()? != 0;
+            let len = chunk.u32()? as usize;
+            let surface = surface_from_buffer(chunk.bytes(len)?)?;
+            project.layers.push(Layer {
+                name,
+                visible,
+                opacity,
+                locked,
+                cells: Cells::from_surface(&surface),
+            });
+        }
+        // chunks added by newer minor versions
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn load(data: &[u8]) -> Result<Project, String> {
+    if !is_project(data) {
+        return Err("not an AsciiPainter project".to_string());
+    }
+    let mut reader = Reader { data, pos: 4 };
+    let major = reader.u16()?;
+    let minor = reader.u16()?;
+    if major > VERSION_MAJOR {
+        return Err(format!(
+            "the project was saved by a newer version of AsciiPainter (format {}.{}); this version can only open format {}.x",
+            major, minor, VERSION_MAJOR
+        ));
+    }
+    let mut project = Project {
+        info: DocumentInfo::new(""),
+        width: 0,
+        height: 0,
+        layers: Vec::new(),
+        tool_settings: Vec::new(),
+    };
+    let mut has_size = false;
+    while reader.pos < data.len() {
+        let tag = reader.bytes(4)?;
+        let len = reader.u32()? as usize;
+        let mut chunk = Reader {
+            data: reader.bytes(len)?,
+            pos: 0,
+        };
+        read_chunk(&mut project, tag, &mut chunk)
+            .map_err(|e| format!("invalid '{}' section: {}", String::from_utf8_lossy(tag), e))?;
+        has_size |= tag == b"SIZE";
+    }
+    if !has_size {
+        return Err("the canvas size is missing".to_string());
+    }
+    if project.layers.is_empty() {
+        return Err("the project has no layers".to_string());
+    }
+    let size = Size::new(project.width, project.height);
+    if project.layers.iter().any(|l| l.cells.size() != size) {
+        return Err(format!(
+            "the layers do not match the canvas size ({}x{})",
+            project.width, project.height
+        ));
+    }
+    Ok(project)
+}
+
+#[ModalWindow(events = ButtonEvents, response = DocumentInfo)]
+pub struct DocumentPropertiesDialog {
+    info: DocumentInfo,
+    title: Handle<TextField>,
+    author: Handle<TextField>,
+    palette: Vec<Handle<ColorPicker>>,
+    ok: Handle<Button>,
+}
+
+impl DocumentPropertiesDialog {
+    pub fn new(info: &DocumentInfo) -> Self {
+        let mut w = Self {
+            base: ModalWindow::new(
+                "Document properties",
+                layout!("a:c,w:50,h:15"),
+                window::Flags::None,
+            ),
+            info: info.clone(),
+            title: Handle::None,
+            author: Handle::None,
+            palette: Vec::new(),
+            ok: Handle::None,
+        };
+        w.add(label!("'Title:',x:1,y:1,w:8,h:1"));
+        w.title = w.add(TextField::new(
+            &info.title,
+            layout!("x:10,y:1,w:36,h:1"),
+            textfield::Flags::None,
+        ));
+        w.add(label!("'Author:',x:1,y:3,w:8,h:1"));
+        w.author = w.add(TextField::new(
+            &info.author,
+            layout!("x:10,y:3,w:36,h:1"),
+            textfield::Flags::None,
+        ));
+        // the palette is shown under the painting, where its colors can be picked for the tools
+        w.add(label!("'Palette:',x:1,y:5,w:8,h:1"));
+        for (index, color) in info.palette.iter().enumerate() {
+            let (x, y) = (10 + (index % 4) as i32 * 9, 5 + (index / 4) as i32);
+            let picker = w.add(ColorPicker::new(*color, Layout::absolute(x, y, 8, 1)));
+            w.palette.push(picker);
+        }
+        w.ok = w.add(button!("&OK,x:11,y:11,w:12"));
+        w.add(button!("&Cancel,x:25,y:11,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for DocumentPropertiesDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.ok {
+            let mut info = self.info.clone();
+            info.title = self.control(self.title).unwrap().text().to_string();
+            info.author = self.control(self.author).unwrap().text().to_string();
+            info.palette = self
+                .palette
+                .iter()
+                .filter_map(|h| self.control(*h).map(|c| c.color()))
+                .collect();
+            self.exit_with(info);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Project {
+        let mut background = Layer::with_cells(
+            "Background",
+            Cells::new(
+                5,
+                3,
+                Character::new('.', Color::White, Color::DarkBlue, CharFlags::None),
+            ),
+        );
+        background.locked = true;
+        let mut top = Layer::new("Top", 5, 3);
+        top.cells.write_char(
+            2,
+            1,
+            Character::new(
+                '╭',
+                Color::RGB(10, 20, 30),
+                Color::Transparent,
+                CharFlags::Bold,
+            ),
+        );
+        top.visible = false;
+        top.opacity = 40;
+        let mut info = DocumentInfo::new("Title");
+        info.author = "Author".to_string();
+        info.palette[3] = Color::RGB(1, 2, 3);
+        info.palette[15] = Color::Transparent;
+        Project {
+            info,
+            width: 5,
+            height: 3,
+            layers: vec![background, top],
+            tool_settings: vec![
+                ("tool".to_string(), "line".to_string()),
+                (
+                    "line.fore".to_string(),
+                    color_to_string(Color::RGB(1, 2, 3)),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let project = sample();
+        let loaded = load(&save(&project)).unwrap();
+        assert_eq!(loaded.info.title, "Title");
+        assert_eq!(loaded.info.author, "Author");
+        assert_eq!(loaded.info.palette, project.info.palette);
+        assert_eq!((loaded.width, loaded.height), (5, 3));
+        assert_eq!(loaded.tool_settings, project.tool_settings);
+        assert_eq!(loaded.layers.len(), 2);
+        for (a, b) in loaded.layers.iter().zip(project.layers.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.visible, b.visible);
+            assert_eq!(a.opacity, b.opacity);
+            assert_eq!(a.locked, b.locked);
+            assert_eq!(a.cells, b.cells);
+        }
+    }
+
+    #[test]
+    fn canvas_size_is_read_from_the_header() {
+        let data = save(&sample());
+        let mut file = std::io::Cursor::new(&data);
+        assert_eq!(read_canvas_size(&mut file), Ok((5, 3)));
+        let mut file = std::io::Cursor::new(&data[..20]);
+        assert!(read_canvas_size(&mut file).is_err());
+    }
+
+    #[test]
+    fn corrupt_projects_are_rejected() {
+        let data = save(&sample());
+        assert!(load(&data[..data.len() - 1]).is_err());
+        assert!(load(b"APRJ\x02\x00\x00\x00").is_err());
+
+        // a layer cell with an invalid color
+        let mut data = data;
+        let last = data.len() - 1;
+        data[last] = 200;
+        assert!(load(&data).is_err());
+    }
+}
@@ -32,22 +32,27 @@ pub struct Selection {
     r: Rect,
     status: Status,
     start_point: Point,
-    allow_resize: bool,
+    // true if the selection was created by dragging towards left / top
+    reversed_x: bool,
+    reversed_y: bool,
 }
 
 impl Selection {
-    pub(crate) fn new(allow_resize: bool) -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             r: Rect::new(0, 0, 0, 0),
             status: Status::None,
             start_point: Point::new(0, 0),
-            allow_resize,
+            reversed_x: false,
+            reversed_y: false,
         }
     }
     pub(crate) fn reset(&mut self) {
         self.r = Rect::new(0, 0, 0, 0);
         self.status = Status::None;
         self.start_point = Point::ORIGIN;
+        self.reversed_x = false;
+        self.reversed_y = false;
     }
     pub(crate) fn is_visible(&self) -> bool {
         !matches!(self.status, Status::None)
@@ -63,6 +68,21 @@ impl Selection {
             self.r.bottom() - 1,
         )
     }
+    // resizes rect() to `size` (the top-left corner stays in place)
+    pub(crate) fn set_size(&mut self, size: Size) {
+        self.r = Rect::with_point_and_size(
+            self.r.top_left(),
+            Size::new(size.width + 2, size.height + 2),
+        );
+    }
+    // the corner of rect() where the user started the selection
+    pub(crate) fn origin(&self) -> Point {
+        let r = self.rect();
+        Point::new(
+            if self.reversed_x { r.right() } else { r.left() },
+            if self.reversed_y { r.bottom() } else { r.top() },
+        )
+    }
     pub(crate) fn paint(&self, surface: &mut Surface, theme: &Theme) {
         let r = self.r;
         if self.is_during_creation() {
@@ -89,16 +109,14 @@ impl Selection {
             surface.fill_vertical_line(r.left(), r.top(), r.bottom(), ch);
             surface.fill_vertical_line(r.right(), r.top(), r.bottom(), ch);
             // markeri
-            if self.allow_resize {
-                surface.write_char(r.left(), r.top(), marker);
-                surface.write_char(r.left(), r.bottom(), marker);
-                surface.write_char(r.right(), r.top(), marker);
-                surface.write_char(r.right(), r.bottom(), marker);
-                surface.write_char(r.center_x(), r.top(), marker);
-                surface.write_char(r.center_x(), r.bottom(), marker);
-                surface.write_char(r.left(), r.center_y(), marker);
-                surface.write_char(r.right(), r.center_y(), marker);
-            }
+            surface.write_char(r.left(), r.top(), marker);
+            surface.write_char(r.left(), r.bottom(), marker);
+            surface.write_char(r.right(), r.top(), marker);
+            surface.write_char(r.right(), r.bottom(), marker);
+            surface.write_char(r.center_x(), r.top(), marker);
+            surface.write_char(r.center_x(), r.bottom(), marker);
+            surface.write_char(r.left(), r.center_y(), marker);
+            surface.write_char(r.right(), r.center_y(), marker);
         }
     }
     fn mouse_pos_in_rect(&self, point: Point) -> MousePosInRect {
@@ -108,26 +126,22 @@ impl Selection {
             && point.y >= r.top()
             && point.y <= r.bottom()
         {
-            if self.allow_resize {
-                if point.x == r.left() && point.y == r.top() {
-                    MousePosInRect::TopLeft
-                } else if point.x == r.right() && point.y == r.top() {
-                    MousePosInRect::TopRight
-                } else if point.x == r.left() && point.y == r.bottom() {
-                    MousePosInRect::BottomLeft
-                } else if point.x == r.right() && point.y == r.bottom() {
-                    MousePosInRect::BottomRight
-                } else if point.x == r.center_x() && point.y == r.top() {
-                    MousePosInRect::TopMargin
-                } else if point.x == r.center_x() && point.y == r.bottom() {
-                    MousePosInRect::BottomMargin
-                } else if point.y == r.center_y() && point.x == r.left() {
-                    MousePosInRect::LeftMargin
-                } else if point.y == r.center_y() && point.x == r.right() {
-                    MousePosInRect::RightMargin
-                } else {
-                    MousePosInRect::Inside
-                }
+            if point.x == r.left() && point.y == r.top() {
+                MousePosInRect::TopLeft
+            } else if point.x == r.right() && point.y == r.top() {
+                MousePosInRect::TopRight
+            } else if point.x == r.left() && point.y == r.bottom() {
+                MousePosInRect::BottomLeft
+            } else if point.x == r.right() && point.y == r.bottom() {
+                MousePosInRect::BottomRight
+            } else if point.x == r.center_x() && point.y == r.top() {
+                MousePosInRect::TopMargin
+            } else if point.x == r.center_x() && point.y == r.bottom() {
+                MousePosInRect::BottomMargin
+            } else if point.y == r.center_y() && point.x == r.left() {
+                MousePosInRect::LeftMargin
+            } else if point.y == r.center_y() && point.x == r.right() {
+                MousePosInRect::RightMargin
             } else {
                 MousePosInRect::Inside
             }
@@ -195,6 +209,8 @@ impl Selection {
                 let t = self.start_point.y.min(data.y);
                 let b = self.start_point.y.max(data.y);
                 self.r = Rect::new(l, t, r, b);
+                self.reversed_x = data.x < self.start_point.x;
+                self.reversed_y = data.y < self.start_point.y;
                 true
             }
             Status::Drag => {
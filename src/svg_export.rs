@@ -0,0 +1,200 @@
+use std::fmt::Write;
+
+use appcui::prelude::*;
+
+use crate::colors;
+
+#[derive(Copy, Clone)]
+pub struct SvgExportOptions {
+    pub cell_width: u32,
+    pub cell_height: u32,
+}
+
+// color used for transparent foregrounds (the default console text color)
+const DEFAULT_FORE: (u8, u8, u8) = colors::PALETTE[7];
+
+fn hex(rgb: (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+fn escape(output: &mut String, ch: char) {
+    match ch {
+        '&' => output.push_str("&amp;"),
+        '<' => output.push_str("&lt;"),
+        '>' => output.push_str("&gt;"),
+        c if c == 0 as char => output.push(' '),
+        c => output.push(c),
+    }
+}
+
+fn write_text(
+    output: &mut String,
+    x: i32,
+    y: i32,
+    text: &str,
+    fore: Color,
+    flags: CharFlags,
+    options: &SvgExportOptions,
+) {
+    if text.trim_matches(' ').is_empty() {
+        return;
+    }
+    let len = text.chars().count() as u32;
+    let _ = write!(
+        output,
+        "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" fill=\"{}\"",
+        x as u32 * options.cell_width,
+        y as u32 * options.cell_height + options.cell_height / 2,
+        len * options.cell_width,
+        hex(colors::rgb(fore).unwrap_or(DEFAULT_FORE)),
+    );
+    if flags.contains(CharFlags::Bold) {
+        output.push_str(" font-weight=\"bold\"");
+    }
+    if flags.contains(CharFlags::Italic) {
+        output.push_str(" font-style=\"italic\"");
+    }
+    if flags.contains(CharFlags::Underline) {
+        output.push_str(" text-decoration=\"underline\"");
+    }
+    output.push('>');
+    for ch in text.chars() {
+        escape(output, ch);
+    }
+    output.push_str("</text>\n");
+}
+
+pub fn export(surface: &Surface, options: &SvgExportOptions) -> String {
+    let size = surface.size();
+    let width = size.width * options.cell_width;
+    let height = size.height * options.cell_height;
+    let mut output = String::new();
+    let _ = writeln!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+        width, height
+    );
+    let _ = writeln!(
+        output,
+        "<g font-family=\"monospace\" font-size=\"{}\" dominant-baseline=\"central\" xml:space=\"preserve\">",
+        options.cell_height * 4 / 5
+    );
+    for y in 0..size.height as i32 {
+        // backgrounds: one rectangle per run of cells with the same color
+        let mut x = 0;
+        while x < size.width as i32 {
+            let back = surface
+                .char(x, y)
+                .map(|c| c.background)
+                .unwrap_or(Color::Transparent);
+            let start = x;
+            while x < size.width as i32 && surface.char(x, y).map(|c| c.background) == Some(back) {
+                x += 1;
+            }
+            x = x.max(start + 1);
+            if let Some(rgb) = colors::rgb(back) {
+                let _ = writeln!(
+                    output,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    start as u32 * options.cell_width,
+                    y as u32 * options.cell_height,
+                    (x - start) as u32 * options.cell_width,
+                    options.cell_height,
+                    hex(rgb)
+                );
+            }
+        }
+        // foregrounds: one text element per run of cells with the same color and flags
+        let mut run = String::new();
+        let mut run_start = 0;
+        let mut run_attr: Option<(Color, CharFlags)> = None;
+        for x in 0..size.width as i32 {
+            let Some(ch) = surface.char(x, y) else {
+                continue;
+            };
+            let attr = (ch.foreground, ch.flags);
+            // spaces do not break a run, whatever their foreground is
+            let is_space = ch.code == ' ' || ch.code == 0 as char;
+            if run_attr != Some(attr) && !is_space {
+                if let Some((fore, flags)) = run_attr {
+                    write_text(
+                        &mut output,
+                        run_start,
+                        y,
+                        run.trim_end_matches(' '),
+                        fore,
+                        flags,
+                        options,
+                    );
+                }
+                run.clear();
+                run_start = x;
+                run_attr = Some(attr);
+            }
+            if run_attr.is_some() {
+                run.push(ch.code);
+            }
+        }
+        if let Some((fore, flags)) = run_attr {
+            write_text(
+                &mut output,
+                run_start,
+                y,
+                run.trim_end_matches(' '),
+                fore,
+                flags,
+                options,
+            );
+        }
+    }
+    output.push_str("</g>\n</svg>\n");
+    output
+}
+
+#[ModalWindow(events = ButtonEvents, response = SvgExportOptions)]
+pub struct SvgExportDialog {
+    cell_width: Handle<NumericSelector<u32>>,
+    cell_height: Handle<NumericSelector<u32>>,
+    export: Handle<Button>,
+}
+
+impl SvgExportDialog {
+    pub fn new() -> Self {
+        let mut w = Self {
+            base: ModalWindow::new(
+                "Export as SVG",
+                layout!("a:c,w:44,h:9"),
+                window::Flags::None,
+            ),
+            cell_width: Handle::None,
+            cell_height: Handle::None,
+            export: Handle::None,
+        };
+        w.add(label!("'Cell width:',x:1,y:1,w:14,h:1"));
+        w.cell_width = w.add(numericselector!(
+            "u32,value:8,min:2,max:64,step:1,x:16,y:1,w:25"
+        ));
+        w.add(label!("'Cell height:',x:1,y:3,w:14,h:1"));
+        w.cell_height = w.add(numericselector!(
+            "u32,value:16,min:2,max:128,step:1,x:16,y:3,w:25"
+        ));
+        w.export = w.add(button!("&Export,x:8,y:5,w:12"));
+        w.add(button!("&Cancel,x:22,y:5,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for SvgExportDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.export {
+            let options = SvgExportOptions {
+                cell_width: self.control(self.cell_width).unwrap().value(),
+                cell_height: self.control(self.cell_height).unwrap().value(),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
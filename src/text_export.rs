@@ -0,0 +1,160 @@
+use appcui::prelude::*;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum LineEnding {
+    #[VariantInfo(name = "LF", description = "Unix line endings (\\n)")]
+    Lf,
+    #[VariantInfo(name = "CRLF", description = "Windows line endings (\\r\\n)")]
+    CrLf,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumSelector)]
+pub enum TextWrap {
+    #[VariantInfo(name = "None", description = "Only the characters")]
+    None,
+    #[VariantInfo(name = "Code fence", description = "Markdown code block (```)")]
+    CodeFence,
+    #[VariantInfo(name = "// comment", description = "Every line starts with //")]
+    SlashComment,
+    #[VariantInfo(name = "# comment", description = "Every line starts with #")]
+    HashComment,
+}
+
+#[derive(Copy, Clone)]
+pub struct TextExportOptions {
+    pub trim_trailing_spaces: bool,
+    pub crop: bool,
+    pub line_ending: LineEnding,
+    pub wrap: TextWrap,
+}
+
+fn is_empty(ch: &Character) -> bool {
+    ch.code == ' ' || ch.code == 0 as char
+}
+
+// smallest rectangle (left, top, right, bottom) that contains all non-empty cells
+fn content_bounds(surface: &Surface) -> Option<(i32, i32, i32, i32)> {
+    let size = surface.size();
+    let mut bounds: Option<(i32, i32, i32, i32)> = None;
+    for y in 0..size.height as i32 {
+        for x in 0..size.width as i32 {
+            if surface.char(x, y).is_none_or(is_empty) {
+                continue;
+            }
+            bounds = Some(match bounds {
+                None => (x, y, x, y),
+                Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x), b.max(y)),
+            });
+        }
+    }
+    bounds
+}
+
+pub fn export(surface: &Surface, options: &TextExportOptions) -> String {
+    let size = surface.size();
+    let bounds = if options.crop {
+        content_bounds(surface)
+    } else if size.width > 0 && size.height > 0 {
+        Some((0, 0, size.width as i32 - 1, size.height as i32 - 1))
+    } else {
+        None
+    };
+    let mut lines = Vec::new();
+    if let Some((left, top, right, bottom)) = bounds {
+        for y in top..=bottom {
+            let mut line: String = (left..=right)
+                .map(|x| match surface.char(x, y) {
+                    Some(ch) if !is_empty(ch) => ch.code,
+                    _ => ' ',
+                })
+                .collect();
+            if options.trim_trailing_spaces {
+                line.truncate(line.trim_end_matches(' ').len());
+            }
+            lines.push(line);
+        }
+    }
+    let prefix = match options.wrap {
+        TextWrap::SlashComment => "//",
+        TextWrap::HashComment => "#",
+        TextWrap::None | TextWrap::CodeFence => "",
+    };
+    let eol = match options.line_ending {
+        LineEnding::Lf => "\n",
+        LineEnding::CrLf => "\r\n",
+    };
+    let mut output = String::new();
+    if options.wrap == TextWrap::CodeFence {
+        output.push_str("```");
+        output.push_str(eol);
+    }
+    for line in lines {
+        output.push_str(prefix);
+        if !prefix.is_empty() && !line.is_empty() {
+            output.push(' ');
+        }
+        output.push_str(&line);
+        output.push_str(eol);
+    }
+    if options.wrap == TextWrap::CodeFence {
+        output.push_str("```");
+        output.push_str(eol);
+    }
+    output
+}
+
+#[ModalWindow(events = ButtonEvents, response = TextExportOptions)]
+pub struct TextExportDialog {
+    trim: Handle<CheckBox>,
+    crop: Handle<CheckBox>,
+    line_ending: Handle<Selector<LineEnding>>,
+    wrap: Handle<Selector<TextWrap>>,
+    export: Handle<Button>,
+}
+
+impl TextExportDialog {
+    pub fn new() -> Self {
+        let mut w = Self {
+            base: ModalWindow::new(
+                "Export as text",
+                layout!("a:c,w:44,h:12"),
+                window::Flags::None,
+            ),
+            trim: Handle::None,
+            crop: Handle::None,
+            line_ending: Handle::None,
+            wrap: Handle::None,
+            export: Handle::None,
+        };
+        w.trim = w.add(checkbox!(
+            "'Trim trailing spaces',x:1,y:1,w:40,h:1,checked:true"
+        ));
+        w.crop = w.add(checkbox!(
+            "'Crop to content',x:1,y:2,w:40,h:1,checked:false"
+        ));
+        w.add(label!("'Line endings:',x:1,y:4,w:14,h:1"));
+        w.line_ending = w.add(selector!("LineEnding,x:16,y:4,w:25,value:Lf"));
+        w.add(label!("'Wrap in:',x:1,y:6,w:14,h:1"));
+        w.wrap = w.add(selector!("TextWrap,x:16,y:6,w:25,value:None"));
+        w.export = w.add(button!("&Export,x:8,y:8,w:12"));
+        w.add(button!("&Cancel,x:22,y:8,w:12"));
+        w
+    }
+}
+
+impl ButtonEvents for TextExportDialog {
+    fn on_pressed(&mut self, handle: Handle<Button>) -> EventProcessStatus {
+        if handle == self.export {
+            let options = TextExportOptions {
+                trim_trailing_spaces: self.control(self.trim).unwrap().is_checked(),
+                crop: self.control(self.crop).unwrap().is_checked(),
+                line_ending: self.control(self.line_ending).unwrap().value(),
+                wrap: self.control(self.wrap).unwrap().value(),
+            };
+            self.exit_with(options);
+        } else {
+            self.exit();
+        }
+        EventProcessStatus::Processed
+    }
+}
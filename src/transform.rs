@@ -0,0 +1,306 @@
+use appcui::prelude::*;
+
+use crate::layer;
+
+// glyphs that change into each other when mirrored left <-> right
+const MIRROR_HORIZONTAL: &[(char, char)] = &[
+    ('┌', '┐'),
+    ('└', '┘'),
+    ('├', '┤'),
+    ('┏', '┓'),
+    ('┗', '┛'),
+    ('┣', '┫'),
+    ('╔', '╗'),
+    ('╚', '╝'),
+    ('╠', '╣'),
+    ('╓', '╖'),
+    ('╙', '╜'),
+    ('╒', '╕'),
+    ('╘', '╛'),
+    ('╟', '╢'),
+    ('╞', '╡'),
+    ('╭', '╮'),
+    ('╰', '╯'),
+    ('╴', '╶'),
+    ('/', '\\'),
+    ('╱', '╲'),
+    ('<', '>'),
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('◄', '►'),
+    ('←', '→'),
+    ('▌', '▐'),
+    ('▘', '▝'),
+    ('▖', '▗'),
+    ('▛', '▜'),
+    ('▙', '▟'),
+    ('▚', '▞'),
+    ('◢', '◣'),
+    ('◤', '◥'),
+];
+
+// glyphs that change into each other when mirrored top <-> bottom
+const MIRROR_VERTICAL: &[(char, char)] = &[
+    ('┌', '└'),
+    ('┐', '┘'),
+    ('┬', '┴'),
+    ('┏', '┗'),
+    ('┓', '┛'),
+    ('┳', '┻'),
+    ('╔', '╚'),
+    ('╗', '╝'),
+    ('╦', '╩'),
+    ('╓', '╙'),
+    ('╖', '╜'),
+    ('╒', '╘'),
+    ('╕', '╛'),
+    ('╥', '╨'),
+    ('╤', '╧'),
+    ('╭', '╰'),
+    ('╮', '╯'),
+    ('╵', '╷'),
+    ('/', '\\'),
+    ('╱', '╲'),
+    ('▲', '▼'),
+    ('↑', '↓'),
+    ('^', 'v'),
+    ('⏜', '⏝'),
+    ('⎴', '⎵'),
+    ('⏞', '⏟'),
+    ('▀', '▄'),
+    ('▘', '▖'),
+    ('▝', '▗'),
+    ('▛', '▙'),
+    ('▜', '▟'),
+    ('▚', '▞'),
+    ('◢', '◥'),
+    ('◣', '◤'),
+];
+
+// every glyph is followed by the glyph it becomes when rotated by 90° clockwise
+const ROTATE_CLOCKWISE: &[[char; 4]] = &[
+    ['─', '│', '─', '│'],
+    ['━', '┃', '━', '┃'],
+    ['═', '║', '═', '║'],
+    ['┄', '┆', '┄', '┆'],
+    ['┅', '┇', '┅', '┇'],
+    ['┈', '┊', '┈', '┊'],
+    ['┉', '┋', '┉', '┋'],
+    ['╌', '╎', '╌', '╎'],
+    ['╍', '╏', '╍', '╏'],
+    ['╪', '╫', '╪', '╫'],
+    ['┌', '┐', '┘', '└'],
+    ['├', '┬', '┤', '┴'],
+    ['┏', '┓', '┛', '┗'],
+    ['┣', '┳', '┫', '┻'],
+    ['╔', '╗', '╝', '╚'],
+    ['╠', '╦', '╣', '╩'],
+    ['╓', '╕', '╜', '╘'],
+    ['╒', '╖', '╛', '╙'],
+    ['╟', '╤', '╢', '╧'],
+    ['╞', '╥', '╡', '╨'],
+    ['╭', '╮', '╯', '╰'],
+    ['╴', '╵', '╶', '╷'],
+    ['-', '|', '-', '|'],
+    ['/', '\\', '/', '\\'],
+    ['╱', '╲', '╱', '╲'],
+    ['►', '▼', '◄', '▲'],
+    ['→', '↓', '←', '↑'],
+    // chevrons (the arrow heads of the connectors) and brackets
+    ['<', '^', '>', 'v'],
+    ['(', '⏜', ')', '⏝'],
+    ['[', '⎴', ']', '⎵'],
+    ['{', '⏞', '}', '⏟'],
+    ['▀', '▐', '▄', '▌'],
+    ['▘', '▝', '▗', '▖'],
+    ['▛', '▜', '▟', '▙'],
+    ['▚', '▞', '▚', '▞'],
+    ['◢', '◣', '◤', '◥'],
+];
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Transform {
+    FlipHorizontal,
+    FlipVertical,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+}
+
+fn mirror(pairs: &[(char, char)], ch: char) -> char {
+    pairs
+        .iter()
+        .find_map(|&(a, b)| {
+            if a == ch {
+                Some(b)
+            } else if b == ch {
+                Some(a)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(ch)
+}
+
+// `turns` steps of 90° clockwise
+fn rotate(ch: char, turns: usize) -> char {
+    ROTATE_CLOCKWISE
+        .iter()
+        .find_map(|cycle| {
+            let index = cycle.iter().position(|&c| c == ch)?;
+            Some(cycle[(index + turns) % 4])
+        })
+        .unwrap_or(ch)
+}
+
+impl Transform {
+    fn glyph(self, ch: char) -> char {
+        match self {
+            Transform::FlipHorizontal => mirror(MIRROR_HORIZONTAL, ch),
+            Transform::FlipVertical => mirror(MIRROR_VERTICAL, ch),
+            Transform::RotateClockwise => rotate(ch, 1),
+            Transform::RotateCounterClockwise => rotate(ch, 3),
+            Transform::Rotate180 => mirror(MIRROR_VERTICAL, mirror(MIRROR_HORIZONTAL, ch)),
+        }
+    }
+
+    // size of the transformed surface
+    pub fn size(self, size: Size) -> Size {
+        match self {
+            Transform::RotateClockwise | Transform::RotateCounterClockwise => {
+                Size::new(size.height, size.width)
+            }
+            _ => size,
+        }
+    }
+
+    // the transformed surface, with direction-sensitive glyphs (box lines, arrows, half blocks, ...)
+    // replaced so that drawings keep their meaning
+    pub fn apply(self, surface: &Surface) -> Surface {
+        let size = surface.size();
+        let (w, h) = (size.width as i32, size.height as i32);
+        let new_size = self.size(size);
+        layer::surface_from_cells(new_size.width, new_size.height, |x, y| {
+            let (sx, sy) = match self {
+                Transform::FlipHorizontal => (w - 1 - x, y),
+                Transform::FlipVertical => (x, h - 1 - y),
+                Transform::RotateClockwise => (y, h - 1 - x),
+                Transform::RotateCounterClockwise => (w - 1 - y, x),
+                Transform::Rotate180 => (w - 1 - x, h - 1 - y),
+            };
+            let mut ch = surface.char(sx, sy).copied().unwrap_or(layer::EMPTY);
+            ch.code = self.glyph(ch.code);
+            ch
+        })
+    }
+}
+
+// source index (nearest neighbour) of cell `i` when `old` cells are stretched to `new`; with `keep_ends` the
+// first and the last cell are kept as they are and only the ones between them are stretched
+fn source_index(i: u32, old: u32, new: u32, keep_ends: bool) -> u32 {
+    if keep_ends && old > 2 && new > 2 {
+        match i {
+            0 => 0,
+            _ if i == new - 1 => old - 1,
+            _ => 1 + source_index(i - 1, old - 2, new - 2, false),
+        }
+    } else {
+        ((2 * i + 1) * old / (2 * new)).min(old - 1)
+    }
+}
+
+// the surface resampled to `size`; `nine_slice` keeps the corners and the border lines (box drawings)
+// and stretches only the cells inside them
+pub fn scale(surface: &Surface, size: Size, nine_slice: bool) -> Surface {
+    let old = surface.size();
+    if old.width == 0 || old.height == 0 {
+        return layer::transparent_surface(size.width, size.height);
+    }
+    layer::surface_from_cells(size.width, size.height, |x, y| {
+        let sx = source_index(x as u32, old.width, size.width, nine_slice);
+        let sy = source_index(y as u32, old.height, size.height, nine_slice);
+        surface
+            .char(sx as i32, sy as i32)
+            .copied()
+            .unwrap_or(layer::EMPTY)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Transform::*;
+
+    // every glyph of the tables
+    fn glyphs() -> Vec<char> {
+        MIRROR_HORIZONTAL
+            .iter()
+            .chain(MIRROR_VERTICAL)
+            .flat_map(|&(a, b)| [a, b])
+            .chain(ROTATE_CLOCKWISE.iter().flatten().copied())
+            .collect()
+    }
+
+    fn rows(surface: &Surface) -> Vec<String> {
+        let size = surface.size();
+        (0..size.height as i32)
+            .map(|y| {
+                (0..size.width as i32)
+                    .map(|x| surface.char(x, y).unwrap().code)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transforms_that_cancel_out() {
+        for transforms in [
+            &[FlipHorizontal, FlipHorizontal][..],
+            &[FlipVertical, FlipVertical],
+            &[Rotate180, Rotate180],
+            &[RotateClockwise; 4],
+            &[RotateCounterClockwise; 4],
+            &[RotateClockwise, RotateCounterClockwise],
+            &[RotateCounterClockwise, RotateClockwise],
+        ] {
+            for ch in glyphs() {
+                let result = transforms.iter().fold(ch, |ch, t| t.glyph(ch));
+                assert_eq!(
+                    result,
+                    ch,
+                    "'{}' is changed by {} transforms",
+                    ch,
+                    transforms.len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_180_is_two_clockwise_rotations() {
+        for ch in glyphs() {
+            let twice = RotateClockwise.glyph(RotateClockwise.glyph(ch));
+            assert_eq!(Rotate180.glyph(ch), twice, "'{}'", ch);
+        }
+    }
+
+    #[test]
+    fn apply_moves_the_cells() {
+        let surface = layer::surface_from_cells(3, 2, |x, y| {
+            let code = ['a', 'b', 'c', 'd', 'e', 'f'][(y * 3 + x) as usize];
+            Character::new(code, Color::White, Color::Black, CharFlags::None)
+        });
+        for (transform, expected) in [
+            (FlipHorizontal, &["cba", "fed"][..]),
+            (FlipVertical, &["def", "abc"]),
+            (Rotate180, &["fed", "cba"]),
+            (RotateClockwise, &["da", "eb", "fc"]),
+            (RotateCounterClockwise, &["cf", "be", "ad"]),
+        ] {
+            let result = transform.apply(&surface);
+            assert_eq!(result.size(), transform.size(surface.size()));
+            assert_eq!(rows(&result), expected);
+        }
+    }
+}